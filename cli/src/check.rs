@@ -0,0 +1,217 @@
+use {
+    std::{collections::BTreeMap, path::Path},
+    serde::Serialize,
+
+    curseforge::prelude::*,
+    crate::{modpack::*, loader_check::loader_version_allows}
+};
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CheckIssueKind {
+    InvalidJar,
+
+    // `Path` entry of modpack points to nothing
+    MissingFile,
+    MissingDependency,
+    DependencyVersion,
+    Incompatible,
+    DuplicateMod,
+    WrongLoader,
+    LoaderVersion,
+    WrongMinecraft
+}
+
+impl CheckIssueKind {
+    // Mods often run fine outside of versions they declare, so these don't fail the check
+    pub fn severity(&self) -> Severity {
+        match self {
+            CheckIssueKind::WrongMinecraft | CheckIssueKind::LoaderVersion => Severity::Warning,
+            _ => Severity::Error
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning
+}
+
+#[derive(Serialize)]
+pub struct CheckIssue {
+    pub kind: CheckIssueKind,
+    pub severity: Severity,
+    pub file: String,
+    pub message: String
+}
+
+impl CheckIssue {
+    fn new(kind: CheckIssueKind, file: String, message: String) -> CheckIssue {
+        CheckIssue{kind, severity: kind.severity(), file, message}
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+#[derive(Serialize)]
+pub struct CheckReport {
+    pub dir: String,
+    pub jars: usize,
+    pub issues: Vec<CheckIssue>
+}
+
+const LOADERS: [ModLoader; 4] = [ModLoader::Fabric, ModLoader::Quilt, ModLoader::Forge, ModLoader::NeoForge];
+
+// Installed mod id with its jar
+struct Provider<'a> {
+    file: &'a str,
+    version: Option<&'a str>
+}
+
+// Local files of modpack must exist and mods among them must be readable jars
+fn check_local(cfg: &ModpackCfg, issues: &mut Vec<CheckIssue>) {
+    for mod_ in &cfg.mc.mods {
+        let path = match &mod_.path {
+            Some(path) => path,
+            None => { continue; }
+        };
+
+        let source = cfg.pack_path(path);
+        if !source.is_file() {
+            issues.push(CheckIssue::new(CheckIssueKind::MissingFile, path.clone(),
+                                        "local file doesn't exist".to_string()));
+        } else if mod_.kind.is_mod() && JarMeta::read(&source).is_none() {
+            issues.push(CheckIssue::new(CheckIssueKind::InvalidJar, path.clone(),
+                                        "not a valid jar".to_string()));
+        }
+    }
+}
+
+// Checks jars in `dir` of `side` installation against each other and the modpack using their metadata only
+pub fn check_mods(cfg: &ModpackCfg, dir: &Path, side: Side) -> RResult<CheckReport> {
+    let pack = &cfg.mc;
+    let mut paths = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().map(|e| e == "jar").unwrap_or(false) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut issues = vec![];
+    check_local(cfg, &mut issues);
+
+    let mut jars = vec![];
+    for path in &paths {
+        let file = path.file_name().unwrap().to_string_lossy().to_string();
+        match JarMeta::read(path) {
+            Some(jar) => jars.push((file, jar)),
+            None => issues.push(CheckIssue::new(CheckIssueKind::InvalidJar, file, "not a valid jar".to_string()))
+        }
+    }
+
+    let mut providers: BTreeMap<&str, Vec<Provider>> = BTreeMap::new();
+    let mut owners: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (file, jar) in &jars {
+        for mod_ in jar.all_mods(&pack.loader) {
+            for id in std::iter::once(&mod_.id).chain(&mod_.provides) {
+                providers.entry(id).or_default().push(Provider{file, version: mod_.version.as_deref()});
+            }
+        }
+
+        for mod_ in jar.for_loader(&pack.loader).iter().flat_map(|m| &m.mods) {
+            owners.entry(&mod_.id).or_default().push(file);
+        }
+    }
+
+    for (id, files) in owners.iter().filter(|(_, files)| files.len() > 1) {
+        issues.push(CheckIssue::new(CheckIssueKind::DuplicateMod, files.join(", "),
+                                    format!("{} is installed {} times", id, files.len())));
+    }
+
+    let minecraft = McVersion::parse(&pack.version).map(|v| v.to_string());
+    let loader_id = loader_mod_id(&pack.loader);
+    let loader_version = pack.loader_version.as_ref().and_then(|v| VersionRange::parse(v));
+
+    for (file, jar) in &jars {
+        let meta = match jar.for_loader(&pack.loader) {
+            Some(meta) => meta,
+            None => {
+                // Jars without any metadata are plain libraries
+                if let Some(other) = jar.metadata.first() {
+                    issues.push(CheckIssue::new(CheckIssueKind::WrongLoader, file.clone(),
+                                                format!("made for {}, modpack uses {}", other.loader(), pack.loader)));
+                }
+                continue;
+            }
+        };
+
+        for mod_ in &meta.mods {
+            let name = mod_.name.as_ref().unwrap_or(&mod_.id);
+            for dep in &mod_.dependencies {
+                let other_side = (side == Side::Client && !dep.side.on_client())
+                    || (side == Side::Server && !dep.side.on_server());
+                if dep.id == mod_.id || dep.id == "java" || dep.kind == DependencyKind::Optional || other_side {
+                    continue;
+                }
+
+                if dep.id == "minecraft" {
+                    let fits = match (&minecraft, &dep.range) {
+                        (Some(version), Some(range)) => range.contains(version),
+                        _ => true
+                    };
+                    if !fits && dep.kind == DependencyKind::Required {
+                        issues.push(CheckIssue::new(CheckIssueKind::WrongMinecraft, file.clone(),
+                            format!("{} supports minecraft {}, modpack uses {}", name, dep.versions, pack.version)));
+                    }
+                    continue;
+                }
+
+                // Loaders are checked against LoaderVersion, other loaders' ids against nothing
+                if LOADERS.iter().any(|l| loader_mod_id(l) == Some(dep.id.as_str())) {
+                    if let (true, Some(allowed), Some(required)) = (loader_id == Some(dep.id.as_str()), &loader_version, &dep.range) {
+                        if dep.kind == DependencyKind::Required && !loader_version_allows(allowed, required) {
+                            issues.push(CheckIssue::new(CheckIssueKind::LoaderVersion, file.clone(),
+                                format!("{} requires {} {}, LoaderVersion is {}", name, pack.loader,
+                                        dep.versions, pack.loader_version.as_ref().unwrap())));
+                        }
+                    }
+                    continue;
+                }
+
+                let installed = providers.get(dep.id.as_str()).map(|p| p.as_slice()).unwrap_or_default();
+                let matching: Vec<&Provider> = installed.iter().filter(|p| match (&dep.range, p.version) {
+                    (Some(range), Some(version)) => range.contains(version),
+                    _ => true
+                }).collect();
+
+                match dep.kind {
+                    DependencyKind::Required if installed.is_empty() => {
+                        issues.push(CheckIssue::new(CheckIssueKind::MissingDependency, file.clone(),
+                            format!("{} requires {} {}, it isn't installed", name, dep.id, dep.versions)));
+                    },
+                    DependencyKind::Required if matching.is_empty() => {
+                        let found = installed.iter()
+                            .map(|p| format!("{} in {}", p.version.unwrap_or("?"), p.file))
+                            .collect::<Vec<String>>().join(", ");
+                        issues.push(CheckIssue::new(CheckIssueKind::DependencyVersion, file.clone(),
+                            format!("{} requires {} {}, installed {}", name, dep.id, dep.versions, found)));
+                    },
+                    DependencyKind::Incompatible if !matching.is_empty() => {
+                        let found = matching.iter().map(|p| p.file).collect::<Vec<&str>>().join(", ");
+                        issues.push(CheckIssue::new(CheckIssueKind::Incompatible, file.clone(),
+                            format!("{} is incompatible with {} {} ({})", name, dep.id, dep.versions, found)));
+                    },
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    Ok(CheckReport{dir: dir.to_string_lossy().to_string(), jars: paths.len(), issues})
+}
@@ -1,10 +1,14 @@
 use {
     argh::FromArgs,
     colored::*,
-    
+    serde::Serialize,
+    std::path::{Path, PathBuf},
+
     curseforge::prelude::*,
     crate::{
-        config::*, modpack::*
+        config::*, modpack::*, output::*, lock::*,
+        curseforge_pack::*, mrpack::*, packwiz::*, prism::*,
+        launcher::*, instance::*, server_pack::*, loader_check::*, migrate::*, check::*, import_dir::*
     }
 };
 
@@ -25,7 +29,11 @@ pub struct CliApp {
 
     #[argh(option, short = 'l',
            description = "mod loader(e.g. forge)")]
-    pub mod_loader: Option<String>,
+    pub mod_loader: Option<ModLoader>,
+
+    #[argh(option, short = 'k',
+           description = "kind of searched project: mod, resourcepack, shaderpack, datapack or world")]
+    pub kind: Option<ProjectKind>,
 
     #[argh(option, description = "create modpack")]
     pub create_modpack: Option<String>,
@@ -34,13 +42,389 @@ pub struct CliApp {
            description = "download and switch modpack")]
     pub download: Option<String>,
 
-    #[argh(option, short = 's',
-           description = "switch modpack")]
+    #[argh(option, description = "switch modpack, real game directories are moved into backup modpack")]
     pub switch: Option<String>,
 
     #[argh(option, short = 'r',
            description = "remove modpack")]
-    pub remove: Option<String>
+    pub remove: Option<String>,
+
+    #[argh(switch, description = "print machine readable JSON output")]
+    pub json: bool,
+
+    #[argh(subcommand)]
+    pub command: Option<Command>
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+pub enum Command {
+    List(ListCommand),
+    Info(InfoCommand),
+    Outdated(OutdatedCommand),
+    Tree(TreeCommand),
+    Import(ImportCommand),
+    Export(ExportCommand),
+    Profile(ProfileCommand),
+    Switch(SwitchCommand),
+    Status(StatusCommand),
+    ServerPack(ServerPackCommand),
+    InstallLoader(InstallLoaderCommand),
+    Migrate(MigrateCommand),
+    Check(CheckCommand),
+    Scan(ScanCommand),
+    ShowEffective(ShowEffectiveCommand)
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "list",
+       description = "list mods of modpack with their dependencies")]
+pub struct ListCommand {
+    #[argh(positional, description = "modpack file")]
+    pub pack: String
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "info",
+       description = "show information about mod")]
+pub struct InfoCommand {
+    #[argh(positional, description = "mod id on CurseForge")]
+    pub id: usize
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "outdated",
+       description = "show pinned mods that have newer files")]
+pub struct OutdatedCommand {
+    #[argh(positional, description = "modpack file")]
+    pub pack: String
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "tree",
+       description = "show dependency tree of modpack")]
+pub struct TreeCommand {
+    #[argh(positional, description = "modpack file")]
+    pub pack: String
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "import",
+       description = "import modpack from another format")]
+pub struct ImportCommand {
+    #[argh(subcommand)]
+    pub format: ImportFormat
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+pub enum ImportFormat {
+    CurseForge(ImportCurseForge),
+    Mrpack(ImportMrpack),
+    Packwiz(ImportPackwiz),
+    Prism(ImportPrism),
+    Dir(ImportDir)
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "curseforge",
+       description = "import CurseForge modpack zip")]
+pub struct ImportCurseForge {
+    #[argh(positional, description = "modpack zip file")]
+    pub file: String,
+
+    #[argh(option, short = 'o',
+           description = "output modpack file")]
+    pub output: Option<String>
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "mrpack",
+       description = "import Modrinth .mrpack file")]
+pub struct ImportMrpack {
+    #[argh(positional, description = "mrpack file")]
+    pub file: String,
+
+    #[argh(option, short = 'o',
+           description = "output modpack file")]
+    pub output: Option<String>
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "packwiz",
+       description = "import packwiz repository")]
+pub struct ImportPackwiz {
+    #[argh(positional, description = "directory with pack.toml")]
+    pub dir: String,
+
+    #[argh(option, short = 'o',
+           description = "output modpack file")]
+    pub output: Option<String>
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "prism",
+       description = "import MultiMC or Prism Launcher instance")]
+pub struct ImportPrism {
+    #[argh(positional, description = "instance directory")]
+    pub dir: String,
+
+    #[argh(option, short = 'o',
+           description = "output modpack file")]
+    pub output: Option<String>
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "dir",
+       description = "import mods directory, jars are looked up on CurseForge and Modrinth")]
+pub struct ImportDir {
+    #[argh(positional, description = "mods directory")]
+    pub dir: String,
+
+    #[argh(option, short = 'o',
+           description = "output modpack file")]
+    pub output: Option<String>,
+
+    #[argh(option, description = "minecraft version, detected from jars if omitted")]
+    pub version: Option<String>,
+
+    #[argh(option, description = "mod loader, detected from jars if omitted")]
+    pub loader: Option<ModLoader>
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "export",
+       description = "export modpack to another format")]
+pub struct ExportCommand {
+    #[argh(subcommand)]
+    pub format: ExportFormat
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+pub enum ExportFormat {
+    CurseForge(ExportCurseForge),
+    Mrpack(ExportMrpack),
+    Packwiz(ExportPackwiz),
+    Prism(ExportPrism)
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "curseforge",
+       description = "export modpack as CurseForge modpack zip")]
+pub struct ExportCurseForge {
+    #[argh(positional, description = "modpack file")]
+    pub pack: String,
+
+    #[argh(option, short = 'o',
+           description = "output zip file")]
+    pub output: Option<String>,
+
+    #[argh(option, description = "mod loader version(e.g. 47.2.0)")]
+    pub loader_version: Option<String>,
+
+    #[argh(option, description = "modpack version", default = "String::from(\"1.0.0\")")]
+    pub pack_version: String,
+
+    #[argh(option, description = "modpack author")]
+    pub author: Option<String>,
+
+    #[argh(switch, description = "bundle non-CurseForge mods into overrides and pin unpinned mods")]
+    pub bundle_foreign: bool
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "mrpack",
+       description = "export downloaded modpack as Modrinth .mrpack file")]
+pub struct ExportMrpack {
+    #[argh(positional, description = "modpack file")]
+    pub pack: String,
+
+    #[argh(option, short = 'o',
+           description = "output mrpack file")]
+    pub output: Option<String>,
+
+    #[argh(option, description = "mod loader version(e.g. 0.14.21)")]
+    pub loader_version: Option<String>,
+
+    #[argh(option, description = "modpack version", default = "String::from(\"1.0.0\")")]
+    pub pack_version: String,
+
+    #[argh(switch, description = "bundle files from hosts Modrinth doesn't accept into overrides")]
+    pub bundle_foreign: bool
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "packwiz",
+       description = "export downloaded modpack as packwiz repository")]
+pub struct ExportPackwiz {
+    #[argh(positional, description = "modpack file")]
+    pub pack: String,
+
+    #[argh(positional, description = "output directory")]
+    pub dir: String,
+
+    #[argh(option, description = "mod loader version(e.g. 47.2.0)")]
+    pub loader_version: Option<String>
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "prism",
+       description = "create MultiMC or Prism Launcher instance of downloaded modpack")]
+pub struct ExportPrism {
+    #[argh(positional, description = "modpack file")]
+    pub pack: String,
+
+    #[argh(positional, description = "launcher instances directory")]
+    pub instances: String,
+
+    #[argh(option, description = "mod loader version(e.g. 47.2.0)")]
+    pub loader_version: Option<String>
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "profile",
+       description = "add or update official launcher profile with own game directory for modpack")]
+pub struct ProfileCommand {
+    #[argh(positional, description = "modpack file")]
+    pub pack: String,
+
+    #[argh(option, description = "installed mod loader version(e.g. 47.2.0)")]
+    pub loader_version: Option<String>,
+
+    #[argh(option, description = "profile icon, launcher block name or data URL")]
+    pub icon: Option<String>
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "switch",
+       description = "switch modpack, real game directories are moved into backup modpack")]
+pub struct SwitchCommand {
+    #[argh(positional, description = "modpack name")]
+    pub pack: Option<String>,
+
+    #[argh(switch, description = "switch back to previously active modpack")]
+    pub back: bool
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "status",
+       description = "show currently active modpack")]
+pub struct StatusCommand {}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "server-pack",
+       description = "install server side mods and server overrides of modpack into directory")]
+pub struct ServerPackCommand {
+    #[argh(positional, description = "modpack file")]
+    pub pack: String,
+
+    #[argh(positional, description = "server directory")]
+    pub dir: String
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "install-loader",
+       description = "install mod loader of modpack into minecraft directory")]
+pub struct InstallLoaderCommand {
+    #[argh(positional, description = "modpack file")]
+    pub pack: String,
+
+    #[argh(option, description = "mod loader version(e.g. 47.2.0), latest if omitted")]
+    pub loader_version: Option<String>,
+
+    #[argh(option, description = "local Forge/NeoForge installer jar")]
+    pub installer: Option<String>
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "migrate",
+       description = "check which mods of modpack have files for another minecraft version")]
+pub struct MigrateCommand {
+    #[argh(positional, description = "modpack file")]
+    pub pack: String,
+
+    #[argh(option, description = "target minecraft version")]
+    pub to: String,
+
+    #[argh(option, description = "target mod loader, the modpack one if omitted")]
+    pub loader: Option<ModLoader>,
+
+    #[argh(option, short = 'o', description = "write modpack for the target version into file")]
+    pub output: Option<String>
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "check",
+       description = "check dependencies, loader and minecraft version of downloaded mods offline")]
+pub struct CheckCommand {
+    #[argh(positional, description = "modpack file")]
+    pub pack: String,
+
+    #[argh(option, description = "mods directory, the downloaded modpack one if omitted")]
+    pub dir: Option<String>,
+
+    #[argh(switch, description = "directory belongs to server, client only dependencies aren't required")]
+    pub server: bool
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "scan",
+       description = "scan jar or every jar of directory for malware")]
+pub struct ScanCommand {
+    #[argh(positional, description = "jar file or directory")]
+    pub path: String
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "show-effective",
+       description = "print modpack merged with modpacks it extends and includes")]
+pub struct ShowEffectiveCommand {
+    #[argh(positional, description = "modpack file")]
+    pub pack: String
+}
+
+#[derive(Serialize)]
+pub struct ListReport<'a> {
+    pub modpack: &'a MinecraftModpack,
+    pub dependencies: Vec<ModpackMod>,
+
+    // Requirements excluded or replaced by the modpack
+    pub notes: Vec<ResolveNote>
+}
+
+#[derive(Serialize)]
+pub struct EffectiveReport<'a> {
+    pub modpack: &'a MinecraftModpack,
+
+    // Override directories of the whole inheritance chain, later ones win
+    pub overrides: Vec<PathBuf>,
+    pub client_overrides: Vec<PathBuf>,
+    pub server_overrides: Vec<PathBuf>
+}
+
+#[derive(Serialize)]
+pub struct DirStatus {
+    pub dir: String,
+    pub link: Option<String>,
+    pub pack: Option<String>,
+    pub real: bool
+}
+
+#[derive(Serialize)]
+pub struct StatusReport {
+    pub minecraft_path: String,
+    pub active: Option<String>,
+    pub dirs: Vec<DirStatus>,
+    pub history: Vec<String>
+}
+
+#[derive(Serialize)]
+pub struct OutdatedMod {
+    pub id: usize,
+    pub name: String,
+    pub file: usize,
+    pub latest: ModFile
 }
 
 impl CliApp {
@@ -66,6 +450,27 @@ impl CliApp {
         out
     }
 
+    fn print_table(header: &[&str], rows: &[Vec<String>]) {
+        let mut widths: Vec<usize> = header.iter().map(|h| h.len()).collect();
+        for row in rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let line = header.iter().zip(&widths).map(
+            |(h, w)| format!("{:w$}", h, w = *w)
+        ).collect::<Vec<String>>().join("  ");
+        println!("{}", line.bold());
+
+        for row in rows {
+            let line = row.iter().zip(&widths).map(
+                |(c, w)| format!("{:w$}", c, w = *w)
+            ).collect::<Vec<String>>().join("  ");
+            println!("{}", line);
+        }
+    }
+
     fn unwrap_or<T>(r: Option<T>, message: &str) -> T {
         match r {
             Some(s) => s,
@@ -77,33 +482,53 @@ impl CliApp {
     }
 
     pub async fn search_fn(&self, cf: CurseForge) -> RResult<()> {
-        println!("Searching for {}...", self.search.as_ref().unwrap().red());
+        if !self.json {
+            println!("Searching for {}...", self.search.as_ref().unwrap().red());
+        }
+        let kind = self.kind.unwrap_or_default();
         let results = cf.search(
-            self.search.as_ref().unwrap(), self.version.as_ref()
+            self.search.as_ref().unwrap(), self.version.as_ref(), kind
         ).await?;
-        if results.is_empty() {
+        if results.is_empty() && !self.json {
             println!("Nothing was found");
             return Ok(());
         }
 
-        if self.add.is_some() {
+        if self.add.is_some() && !results.is_empty() {
             let first = results.first().unwrap();
             let mut cfg = ModpackCfg::load(self.add.as_ref().unwrap());
             if cfg.mc.has_modid(first.id) {
+                if self.json {
+                    print_json(first);
+                    return Ok(());
+                }
+
                 println!("Already had this one:");
                 println!("{}", Self::dump_modinfo(first));
                 return Ok(());
             }
 
-            cfg.mc.mods.push(ModpackMod::with_id(first.id));
+            let mut entry = ModpackMod::with_mod(first);
+            entry.kind = kind;
+            cfg.mc.mods.push(entry);
             cfg.store();
 
+            if self.json {
+                print_json(first);
+                return Ok(());
+            }
+
             println!("Found & added to modpack:");
             println!("{}", Self::dump_modinfo(first));
 
             return Ok(());
         }
 
+        if self.json {
+            print_json(&results);
+            return Ok(());
+        }
+
         for result in results.iter().rev() {
             println!("{}", Self::dump_modinfo(&result));
         }
@@ -114,79 +539,777 @@ impl CliApp {
     pub async fn create_modpack_fn(&self) -> RResult<()> {
         let pack = ModpackCfg::new(self.create_modpack.as_ref().unwrap().clone(), Self::unwrap_or(
             self.version.as_ref(), "Game version is required for modpack creation").clone(), 
-            self.mod_loader.as_ref().unwrap_or(&"forge".to_string()).clone(),
+            self.mod_loader.clone().unwrap_or_default(),
             self.create_modpack.as_ref().unwrap().clone() + ".yaml");
         pack.store();
 
-        println!("Modpack {}.yaml is successfully created", self.create_modpack.as_ref().unwrap());
+        if self.json {
+            print_json(&pack.mc);
+            return Ok(());
+        }
+
+        println!("Modpack {} is successfully created", pack.file.bold());
 
         Ok(())
     }
 
     pub async fn download_fn(&self, cf: CurseForge) -> RResult<()> {
-        let pack = ModpackCfg::load(self.download.as_ref().unwrap());
-        let version = GameVersion::new(pack.mc.version).with_loader(pack.mc.loader);
-        let mut downloader = MassDownloader::new();
+        let mut pack = ModpackCfg::load(self.download.as_ref().unwrap());
+        if pack.refresh_names(&cf).await {
+            pack.store();
+        }
+
+        let version = pack.mc.game_version();
+        let scan = McGetConfig::lookup().scan_mode();
+        let mut downloader = MassDownloader::new().with_quiet(self.json).with_scan(scan);
         let packs = get_config_location().join("modpacks").join(&pack.mc.name);
         std::fs::create_dir(packs.to_str().unwrap()).unwrap_or_default();
 
-        let urls = pack.mc.mods.iter().filter(
-            |v| v.url.is_some()
+        let direct = pack.mc.mods.iter().filter(
+            |v| v.id.is_none() && v.installed_on(Side::Client)
         ).collect::<Vec<&ModpackMod>>();
 
-        println!("Resolving dependencies...");
-        let deps = resolve_dependencies(&cf, pack.mc.mods.iter().filter(
-            |v| v.id.is_some()
-        ).map(
-            |v| v.id.unwrap()
-        ).collect(), version.clone(), packs.to_str().unwrap().to_string()).await;
+        if !self.json {
+            println!("Resolving dependencies...");
+        }
+        let deps = resolve_dependencies(&cf, pack.mc.pinned_ids_for(Side::Client), version.clone(),
+                                        packs.to_str().unwrap().to_string(), self.json,
+                                        &pack.mc.resolve_rules()).await;
+
+        if !self.json {
+            println!("Downloading mods...");
+        }
+        let instance = instance_dir(&pack.mc.name);
+        let lock = ModpackLock::load(&pack.lock_file());
+
+        // World archives are removed once extracted, locked worlds aren't downloaded again to keep saved progress
+        let worlds: Vec<LockedFile> = lock.iter().flat_map(|l| &l.files)
+            .filter(|f| f.path.starts_with("saves/")).cloned().collect();
+        let extracted = |id: Option<usize>, url: Option<&String>| worlds.iter().any(
+            |f| (id.is_some() && f.id == id) || url.is_some_and(|u| *u == f.url)
+        );
+
+        // Vanilla loads data packs only from `saves/<world>/datapacks`, server pack puts them into its world
+        let datapacks: Vec<String> = pack.mc.mods.iter().filter(|m| m.kind == ProjectKind::DataPack)
+            .map(|m| m.display_name()).collect();
+        if !datapacks.is_empty() {
+            print_message(format!("Skipped data packs, add them to your world's datapacks directory: {}",
+                                  datapacks.join(", ")));
+        }
+
+        for mut target in deps.targets {
+            let id = target.id.unwrap();
+            let kind = pack.mc.kind_of(id);
+            if kind == ProjectKind::DataPack || (kind == ProjectKind::World && extracted(Some(id), None)) {
+                continue;
+            }
+
+            target.required = !pack.mc.is_optional(id);
+            downloader.add_target(target.with_kind(kind, &instance));
+        }
+        for mod_ in direct {
+            if mod_.kind == ProjectKind::DataPack
+                || (mod_.kind == ProjectKind::World && extracted(None, mod_.url.as_ref())) {
+                continue;
+            }
+
+            if let Some(target) = pack.direct_target(mod_, packs.to_str().unwrap().to_string(), lock.as_ref()) {
+                downloader.add_target(target.with_kind(mod_.kind, &instance));
+            }
+        }
+
+        let report = downloader.download(&cf, version).await;
+        if self.json {
+            print_json(&report);
+        } else {
+            for failed in report.failed() {
+                println!("{} {}", "Failed:".red(), failed.name().bold());
+            }
+        }
+
+        if report.has_required_failures() {
+            std::process::exit(1);
+        }
+
+        let issues = check_loader_version(&pack.mc, &report)?;
+        for issue in &issues {
+            let message = format!("{} needs {} {}, but LoaderVersion of the pack is {}", issue.file.bold(),
+                                  pack.mc.loader, issue.requires.bold(), issue.allowed);
+            if self.json { eprintln!("{}", message); } else { println!("{} {}", "Error:".red(), message); }
+        }
+        if !issues.is_empty() {
+            std::process::exit(1);
+        }
+
+        let mut new_lock = ModpackLock::from_report(pack.lock_file(), &report, lock.as_ref());
+        for world in worlds {
+            if !new_lock.files.iter().any(|f| f.path == world.path) {
+                new_lock.files.push(world);
+            }
+        }
+
+        // Failed worlds aren't locked, so they are downloaded again next time
+        let failed_worlds = extract_worlds(&report);
+        for (path, e) in &failed_worlds {
+            print_message(format!("{} can't extract {}: {}", "Failed:".red(), path.bold(), e));
+            let name = Path::new(path).file_name().unwrap().to_string_lossy().to_string();
+            new_lock.files.retain(|f| f.path != format!("saves/{}", name));
+        }
+        new_lock.files.sort_by(|a, b| a.path.cmp(&b.path));
+        new_lock.store();
+
+        if pack.mc.fill_sides(&report) {
+            pack.store();
+        }
+
+        match install_instance(&pack, scan) {
+            Ok(kept) if !self.json => {
+                for file in kept {
+                    println!("Kept modified {}", file.bold());
+                }
+            },
+            Ok(_) => {},
+            Err(e) => {
+                println!("Failed to install overrides: {}", e.to_string().red());
+                std::process::exit(1);
+            }
+        }
+
+        // Download report is the JSON output, switch is only reported to humans
+        let switched = McGetConfig::lookup().switch_modpack(&pack.mc.name);
+        print_message(format!("Switched to {}: {}", switched.modpack.bold(), switched.managed_dirs.join(", ")));
+
+        if !failed_worlds.is_empty() {
+            std::process::exit(1);
+        }
+
+        Ok(())
+    }
+
+    pub async fn list_fn(&self, cmd: &ListCommand, cf: CurseForge) -> RResult<()> {
+        let mut pack = ModpackCfg::load(&cmd.pack);
+        if pack.refresh_names(&cf).await {
+            pack.store();
+        }
+
+        let version = pack.mc.game_version();
+        let deps = resolve_dependencies(&cf, pack.mc.pinned_ids(), version,
+                                        String::new(), true, &pack.mc.resolve_rules()).await;
+
+        let mut tasks = vec![];
+        for dep in deps.targets {
+            let id = dep.id.unwrap();
+            if pack.mc.has_modid(id) {
+                continue;
+            }
+
+            let cf = cf.clone();
+            tasks.push(tokio::spawn(async move {
+                (id, cf.mod_info(id).await.ok())
+            }));
+        }
+
+        let mut dependencies = vec![];
+        for task in tasks {
+            dependencies.push(match task.await {
+                Ok((_, Some(info))) => ModpackMod::with_mod(&info),
+                Ok((id, None)) => ModpackMod::with_id(id),
+                Err(e) => { return Err(e.into()); }
+            });
+        }
+
+        if self.json {
+            print_json(&ListReport{modpack: &pack.mc, dependencies, notes: deps.notes});
+            return Ok(());
+        }
+
+        let mut rows = vec![];
+        let direct = pack.mc.mods.iter().map(|m| (m, "direct"));
+        let transitive = dependencies.iter().map(|m| (m, "transitive"));
+        for (mod_, kind) in direct.chain(transitive) {
+            // Dependencies are installed wherever mods requiring them are, they have no side of their own
+            let side = match kind {
+                "direct" => mod_.side().to_string(),
+                _ => "-".to_string()
+            };
+            rows.push(vec![
+                mod_.display_name(), mod_.kind.to_string(), mod_.source().to_string(),
+                mod_.file.map(|f| f.to_string()).unwrap_or_else(|| "latest".to_string()),
+                side, kind.to_string()
+            ]);
+        }
+
+        println!("{} ({} {}):", pack.mc.name.bold(), pack.mc.loader, pack.mc.version);
+        Self::print_table(&["Name", "Kind", "Source", "File", "Side", "Dependency"], &rows);
+        for note in &deps.notes {
+            println!("{} {}", "Note:".yellow(), note);
+        }
+
+        Ok(())
+    }
+
+    pub async fn info_fn(&self, cmd: &InfoCommand, cf: CurseForge) -> RResult<()> {
+        let info = cf.mod_info(cmd.id).await?;
+        if self.json {
+            print_json(&info);
+            return Ok(());
+        }
+
+        println!("{}", Self::dump_modinfo(&info));
+        for file in &info.latest_files {
+            println!("\t  - {} (file: {}) {}", file.filename.bold(), file.id,
+                     file.versions.join(", "));
+        }
+
+        Ok(())
+    }
+
+    pub async fn outdated_fn(&self, cmd: &OutdatedCommand, cf: CurseForge) -> RResult<()> {
+        let pack = ModpackCfg::load(&cmd.pack);
+        let version = pack.mc.game_version();
+
+        let mut outdated = vec![];
+        for mod_ in &pack.mc.mods {
+            let (id, file) = match (mod_.id, mod_.file) {
+                (Some(id), Some(file)) => (id, file),
+                _ => { continue; }
+            };
+
+            let files = match cf.files(id, version.clone()).await {
+                Ok(r) => r,
+                _ => { continue; }
+            };
+            let latest = match files.latest() {
+                Ok(r) => r.clone(),
+                _ => { continue; }
+            };
+            if latest.id == file {
+                continue;
+            }
+
+            // Pinned file may be made for another version and missing from the filtered ones
+            let pinned = match files.iter().find(|f| f.id == file) {
+                Some(f) => f.clone(),
+                None => match cf.file(id, file).await {
+                    Ok(r) => r,
+                    _ => { continue; }
+                }
+            };
+
+            if latest.is_newer_than(&pinned) {
+                outdated.push(OutdatedMod{id, name: mod_.display_name(), file, latest});
+            }
+        }
+
+        if self.json {
+            print_json(&outdated);
+            return Ok(());
+        }
 
-        println!("Downloading mods...");
-        for target in deps {
-            downloader.add_target(target);
+        if outdated.is_empty() {
+            println!("All pinned mods are up to date");
+            return Ok(());
         }
-        for target in urls {
-            downloader.add_target(DownloadTarget{id: None, url: Some(target.url.as_ref().unwrap().clone()), dest: packs.to_str().unwrap().to_string()});
+
+        let rows = outdated.iter().map(|m| vec![
+            m.name.clone(), m.file.to_string(), m.latest.id.to_string(), m.latest.filename.clone()
+        ]).collect::<Vec<Vec<String>>>();
+        Self::print_table(&["Name", "File", "Latest", "Latest file name"], &rows);
+
+        Ok(())
+    }
+
+    pub async fn import_fn(&self, cmd: &ImportCommand, cf: CurseForge) -> RResult<()> {
+        let scan = McGetConfig::lookup().scan_mode();
+        let pack = match &cmd.format {
+            ImportFormat::CurseForge(args) => import_curseforge(&args.file, args.output.as_ref(), scan)?,
+            ImportFormat::Mrpack(args) => import_mrpack(&args.file, args.output.as_ref(), scan).await?,
+            ImportFormat::Packwiz(args) => import_packwiz(&args.dir, args.output.as_ref())?,
+            ImportFormat::Prism(args) => import_prism(&args.dir, args.output.as_ref(), &cf).await?,
+            ImportFormat::Dir(args) => import_dir(&args.dir, args.output.as_ref(), args.version.as_ref(),
+                                                  args.loader.as_ref(), &cf).await?
+        };
+
+        if self.json {
+            print_json(&pack.mc);
+            return Ok(());
         }
 
-        downloader.download(&cf, version).await;
+        println!("Modpack {} is successfully imported with {} mods", pack.file.bold(), pack.mc.mods.len());
+        let folders = [&pack.mc.overrides, &pack.mc.client_overrides, &pack.mc.server_overrides];
+        for overrides in folders.iter().copied().flatten() {
+            println!("Overrides are extracted to {}", overrides.bold());
+        }
 
+        Ok(())
+    }
+
+    // Modpack with exact minecraft version for exports, profiles and loader installs,
+    // range packs get the newest release they allow
+    async fn load_resolved(file: &str) -> RResult<ModpackCfg> {
+        let mut pack = ModpackCfg::load(file);
+        let installer = LoaderInstaller::new(McGetConfig::lookup().loader_urls().clone());
+        pack.mc.resolved_version = Some(installer.game_version(&pack.mc.version).await?);
+
+        Ok(pack)
+    }
+
+    pub async fn export_fn(&self, cmd: &ExportCommand, cf: CurseForge) -> RResult<()> {
+        let output = match &cmd.format {
+            ExportFormat::CurseForge(args) => {
+                let mut pack = Self::load_resolved(&args.pack).await?;
+                if pack.refresh_names(&cf).await {
+                    pack.store();
+                }
+
+                let opts = CurseForgeExport{
+                    output: args.output.clone().unwrap_or_else(|| pack.mc.name.clone() + ".zip").into(),
+                    loader_version: Self::unwrap_or(args.loader_version.clone()
+                                                        .or_else(|| pack.mc.exact_loader_version()),
+                                                    "Mod loader version is required for CurseForge export"),
+                    version: args.pack_version.clone(),
+                    author: args.author.clone().unwrap_or_else(whoami::username),
+                    bundle_foreign: args.bundle_foreign
+                };
+
+                export_curseforge(&pack, &cf, &opts, McGetConfig::lookup().scan_mode()).await?;
+                opts.output
+            },
+            ExportFormat::Mrpack(args) => {
+                let pack = Self::load_resolved(&args.pack).await?;
+                let opts = MrpackExport{
+                    output: args.output.clone().unwrap_or_else(|| pack.mc.name.clone() + ".mrpack").into(),
+                    loader_version: Self::unwrap_or(args.loader_version.clone()
+                                                        .or_else(|| pack.mc.exact_loader_version()),
+                                                    "Mod loader version is required for Modrinth export"),
+                    version: args.pack_version.clone(),
+                    bundle_foreign: args.bundle_foreign
+                };
+
+                export_mrpack(&pack, &opts)?;
+                opts.output
+            },
+            ExportFormat::Packwiz(args) => {
+                let pack = Self::load_resolved(&args.pack).await?;
+                let opts = PackwizExport{
+                    dir: args.dir.clone().into(),
+                    loader_version: Self::unwrap_or(args.loader_version.clone()
+                                                        .or_else(|| pack.mc.exact_loader_version()),
+                                                    "Mod loader version is required for packwiz export")
+                };
+
+                export_packwiz(&pack, &opts)?;
+                opts.dir
+            },
+            ExportFormat::Prism(args) => {
+                let pack = Self::load_resolved(&args.pack).await?;
+                let loader_version = Self::unwrap_or(args.loader_version.clone()
+                                                         .or_else(|| pack.mc.exact_loader_version()),
+                                                     "Mod loader version is required for Prism Launcher export");
+
+                export_prism(&pack, Path::new(&args.instances), &loader_version)?
+            }
+        };
+
+        if self.json {
+            print_json(&output);
+            return Ok(());
+        }
+
+        println!("Modpack is successfully exported to {}", output.to_string_lossy().bold());
+
+        Ok(())
+    }
+
+    pub async fn profile_fn(&self, cmd: &ProfileCommand) -> RResult<()> {
+        let pack = Self::load_resolved(&cmd.pack).await?;
         let cfg = McGetConfig::lookup();
-        cfg.switch_modpack(&pack.mc.name);
+
+        let game_dir = create_game_dir(&pack, cfg.scan_mode())?;
+        let loader_version = cmd.loader_version.clone().or_else(|| pack.mc.exact_loader_version());
+        let profile = LauncherProfile::new(&pack, &game_dir, loader_version.as_deref(),
+                                           cmd.icon.as_ref())?;
+        profile.store(cfg.minecraft_path())?;
+
+        if self.json {
+            print_json(&profile);
+            return Ok(());
+        }
+
+        println!("Profile {} ({}) uses game directory {}", profile.name.bold(),
+                 profile.last_version_id, profile.game_dir.bold());
 
         Ok(())
     }
 
-    pub fn remove_modpack(&self) {
+    fn dump_tree(node: &DependencyNode, depth: usize) {
+        let name = node.name.clone().unwrap_or_else(|| node.id.to_string());
+        let file = match &node.file {
+            _ if node.excluded => "excluded".yellow(),
+            Some(f) => f.filename.normal(),
+            None => "no matching file".red()
+        };
+        let replaces = match &node.replaces {
+            Some(original) => format!(" {} {}", "replaces".yellow(), original),
+            None => String::new()
+        };
+
+        println!("{}{} ({}){}", "  ".repeat(depth), name.bold(), file, replaces);
+        for dep in &node.dependencies {
+            Self::dump_tree(dep, depth + 1);
+        }
+    }
+
+    pub async fn tree_fn(&self, cmd: &TreeCommand, cf: CurseForge) -> RResult<()> {
+        let pack = ModpackCfg::load(&cmd.pack);
+        let version = pack.mc.game_version();
+        let rules = pack.mc.resolve_rules();
+
+        let mut tree = vec![];
+        let mut cache = TreeCache::default();
+        for (id, pin) in pack.mc.pinned_ids() {
+            tree.push(dependency_tree(&cf, id, pin, version.clone(), &rules, &mut vec![], &mut cache).await);
+        }
+
+        if self.json {
+            print_json(&tree);
+            return Ok(());
+        }
+
+        for node in &tree {
+            Self::dump_tree(node, 0);
+        }
+
+        Ok(())
+    }
+
+    pub fn remove_modpack(&self) -> RResult<()> {
         let pack = self.remove.as_ref().unwrap();
         let cfg_dir = get_config_location().join("modpacks").join(pack);
+        std::fs::remove_dir_all(instance_dir(pack)).unwrap_or_default();
 
-        match std::fs::remove_dir_all(cfg_dir.to_str().unwrap()) {
-            Ok(_) => {
-                println!("{} Removed {}", "Successfully".green(),
-                         cfg_dir.to_str().unwrap().bold());
-            }
+        std::fs::remove_dir_all(&cfg_dir)
+            .map_err(|e| format!("can't remove {}: {}", cfg_dir.display(), e))?;
 
-            Err(e) => {
-                println!("Error: {}", e.to_string().red());
-            }
+        if self.json {
+            print_json(&cfg_dir);
+            return Ok(());
         }
+
+        println!("{} Removed {}", "Successfully".green(), cfg_dir.to_string_lossy().bold());
+
+        Ok(())
     }
 
     pub fn switch_modpack(&self) {
         let pack = self.switch.as_ref().unwrap();
         let cfg = McGetConfig::lookup();
-        cfg.switch_modpack(pack);
+        self.print_switch(&cfg.switch_modpack(pack));
+    }
+
+    pub fn switch_fn(&self, cmd: &SwitchCommand) -> RResult<()> {
+        let cfg = McGetConfig::lookup();
+        let report = match (&cmd.pack, cmd.back) {
+            (None, true) => cfg.switch_back(),
+            (Some(pack), false) => cfg.switch_modpack(pack),
+            (Some(_), true) => { return Err("Modpack name can't be used with --back".into()); },
+            (None, false) => { return Err("Modpack name or --back is required".into()); }
+        };
+        self.print_switch(&report);
+
+        Ok(())
+    }
+
+    fn print_switch(&self, report: &SwitchReport) {
+        if self.json {
+            print_json(report);
+            return;
+        }
+
+        println!("Switched to {}: {}", report.modpack.bold(), report.managed_dirs.join(", "));
+    }
+
+    pub async fn server_pack_fn(&self, cmd: &ServerPackCommand, cf: CurseForge) -> RResult<()> {
+        let pack = ModpackCfg::load(&cmd.pack);
+        let report = build_server_pack(&pack, &cf, Path::new(&cmd.dir), self.json,
+                                       McGetConfig::lookup().scan_mode()).await?;
+
+        if self.json {
+            print_json(&report);
+            return Ok(());
+        }
+
+        for failed in report.download.failed() {
+            println!("{} {}", "Failed:".red(), failed.name().bold());
+        }
+        for name in &report.skipped {
+            println!("{} {} (client only)", "Skipped".yellow(), name.bold());
+        }
+        println!("Server pack of {} is ready in {}", pack.mc.name.bold(), cmd.dir.bold());
+
+        Ok(())
+    }
+
+    pub async fn install_loader_fn(&self, cmd: &InstallLoaderCommand) -> RResult<()> {
+        let pack = Self::load_resolved(&cmd.pack).await?;
+        let cfg = McGetConfig::lookup();
+        let installer = LoaderInstaller::new(cfg.loader_urls().clone());
+        let version = pack.mc.exact_version()?;
+
+        let loader_version = match cmd.loader_version.clone().or_else(|| pack.mc.exact_loader_version()) {
+            Some(v) => v,
+            None => installer.latest_version(&version, &pack.mc.loader).await?
+        };
+
+        let allowed = pack.mc.loader_version.as_ref().and_then(|v| VersionRange::parse(v));
+        if allowed.map(|r| !r.contains(&loader_version)).unwrap_or(false) {
+            return Err(format!("{} {} doesn't match LoaderVersion {} of the pack", pack.mc.loader,
+                               loader_version, pack.mc.loader_version.as_ref().unwrap()).into());
+        }
+        if !self.json {
+            println!("Installing {} {} for {}...", pack.mc.loader.name().bold(), loader_version, version);
+        }
+
+        let profile = installer.profile(&version, &pack.mc.loader, &loader_version,
+                                        cmd.installer.as_ref().map(Path::new)).await?;
+        let report = installer.install(cfg.minecraft_path(), &profile, self.json).await?;
+
+        if self.json {
+            print_json(&report);
+            return Ok(());
+        }
+
+        println!("Installed version {} with {} libraries", report.id.bold(), report.libraries);
+
+        Ok(())
+    }
+
+    pub fn status_fn(&self) -> RResult<()> {
+        let cfg = McGetConfig::lookup();
+
+        let mut dirs = vec![];
+        for dir in DEFAULT_MANAGED_DIRS {
+            let path = cfg.minecraft_path().join(dir);
+            dirs.push(DirStatus{
+                dir: dir.to_string(),
+                link: std::fs::read_link(&path).ok().map(|l| l.to_string_lossy().to_string()),
+                pack: linked_modpack(&path),
+                real: path.is_dir() && !is_symlink(&path)
+            });
+        }
+
+        let report = StatusReport{
+            minecraft_path: cfg.minecraft_path().to_string_lossy().to_string(),
+            active: cfg.active_modpack(),
+            dirs,
+            history: SwitchHistory::load().packs
+        };
+
+        if self.json {
+            print_json(&report);
+            return Ok(());
+        }
+
+        match &report.active {
+            Some(pack) => println!("Active modpack: {}", pack.bold()),
+            None => println!("Active modpack: {}", "none".yellow())
+        }
+
+        let rows: Vec<Vec<String>> = report.dirs.iter()
+            .map(|d| {
+                let state = match (&d.link, d.real) {
+                    (Some(link), _) => link.clone(),
+                    (None, true) => "real directory".to_string(),
+                    (None, false) => "-".to_string()
+                };
+                vec![d.dir.clone(), d.pack.clone().unwrap_or_else(|| "-".to_string()), state]
+            })
+            .collect();
+        Self::print_table(&["Directory", "Modpack", "Target"], &rows);
+
+        if let Some(previous) = report.history.last() {
+            println!("Previous modpack: {}", previous.bold());
+        }
+
+        Ok(())
+    }
+
+    pub async fn migrate_fn(&self, cmd: &MigrateCommand, cf: CurseForge) -> RResult<()> {
+        let pack = ModpackCfg::load(&cmd.pack);
+        let loader = cmd.loader.clone().unwrap_or_else(|| pack.mc.loader.clone());
+        let target = GameVersion::new(cmd.to.clone())
+            .with_loader(loader)
+            .with_policy(pack.mc.version_policy);
+
+        let report = migrate_report(&pack, &cf, &target).await?;
+        if let Some(output) = &cmd.output {
+            migrated_pack(&pack, &report, output)?.store();
+        }
+
+        if self.json {
+            print_json(&report);
+        } else {
+            let rows = report.mods.iter().map(|m| vec![
+                m.name.clone(),
+                match m.status {
+                    Readiness::Ready => "ready",
+                    Readiness::OnlyBeta => "only beta",
+                    Readiness::Missing if m.required => "missing",
+                    Readiness::Missing => "missing (optional)",
+                    Readiness::Unknown => "unknown",
+                    Readiness::LookupFailed => "lookup failed"
+                }.to_string(),
+                m.file.as_ref().map(|f| f.filename.clone()).unwrap_or_default(),
+                m.dependency_of.and_then(|id| report.find(id)).map(|d| d.name.clone()).unwrap_or_default()
+            ]).collect::<Vec<Vec<String>>>();
+            Self::print_table(&["Name", "Status", "File", "Dependency of"], &rows);
+
+            println!("{} of {} mods are ready for {} {}, {} only beta, {} missing, {} lookups failed",
+                     report.count(Readiness::Ready), report.mods.len(), report.loader, report.to.bold(),
+                     report.count(Readiness::OnlyBeta), report.count(Readiness::Missing),
+                     report.count(Readiness::LookupFailed));
+            for m in report.blocking() {
+                println!("{} {} has no file for {}", "Error:".red(), m.name.bold(), report.to);
+            }
+            for m in report.mods.iter().filter(|m| m.status == Readiness::LookupFailed) {
+                println!("{} can't look up files of {}: {}", "Error:".red(), m.name.bold(),
+                         m.error.as_deref().unwrap_or_default());
+            }
+            if let Some(output) = &cmd.output {
+                println!("Modpack for {} is written to {}", report.to, output.bold());
+            }
+        }
+
+        // Report with failed lookups is incomplete
+        if !report.blocking().is_empty() || report.count(Readiness::LookupFailed) > 0 {
+            std::process::exit(1);
+        }
+
+        Ok(())
+    }
+
+    pub fn check_fn(&self, cmd: &CheckCommand) -> RResult<()> {
+        let pack = ModpackCfg::load(&cmd.pack);
+        let dir = match &cmd.dir {
+            Some(dir) => PathBuf::from(dir),
+            None => pack_mods_dir(&pack.mc.name)
+        };
+        if !dir.is_dir() {
+            return Err(format!("{} doesn't exist, download the modpack first", dir.display()).into());
+        }
+
+        let side = if cmd.server { Side::Server } else { Side::Client };
+        let report = check_mods(&pack, &dir, side)?;
+        if self.json {
+            print_json(&report);
+        } else {
+            for issue in &report.issues {
+                let label = if issue.is_error() { "Error:".red() } else { "Warning:".yellow() };
+                println!("{} {}: {}", label, issue.file.bold(), issue.message);
+            }
+
+            if report.issues.is_empty() {
+                println!("Checked {} jars of {}, no issues found", report.jars, pack.mc.name.bold());
+            } else {
+                println!("Checked {} jars of {}, {} issues found", report.jars, pack.mc.name.bold(), report.issues.len());
+            }
+        }
+
+        if report.issues.iter().any(|i| i.is_error()) {
+            std::process::exit(1);
+        }
+
+        Ok(())
+    }
+
+    pub fn scan_fn(&self, cmd: &ScanCommand) -> RResult<()> {
+        let path = Path::new(&cmd.path);
+        if !path.exists() {
+            return Err(format!("{} doesn't exist", cmd.path).into());
+        }
+
+        let report = scan_path(path)?;
+        let findings: usize = report.iter().map(|j| j.findings.len()).sum();
+        let errors = report.iter().filter(|j| j.error.is_some()).count();
+        if self.json {
+            print_json(&report);
+        } else {
+            for jar in &report {
+                if let Some(error) = &jar.error {
+                    println!("{} {}: {}", "Error:".red(), jar.file.bold(), error);
+                }
+                for finding in &jar.findings {
+                    let severity = match finding.severity {
+                        ScanSeverity::Malicious => finding.severity.to_string().red(),
+                        ScanSeverity::Suspicious => finding.severity.to_string().yellow()
+                    };
+                    println!("{} {}: {}", severity, jar.file.bold(), finding);
+                }
+            }
+
+            println!("Scanned {} jars, {} findings, {} unreadable", report.len(), findings, errors);
+        }
+
+        // Jars that can't be read are not known to be clean
+        if findings > 0 || errors > 0 {
+            std::process::exit(1);
+        }
+
+        Ok(())
+    }
+
+    pub fn show_effective_fn(&self, cmd: &ShowEffectiveCommand) -> RResult<()> {
+        let pack = ModpackCfg::load(&cmd.pack);
+        let report = EffectiveReport{
+            modpack: &pack.mc,
+            overrides: pack.overrides_dirs(),
+            client_overrides: pack.client_overrides_dirs(),
+            server_overrides: pack.server_overrides_dirs()
+        };
+        if self.json {
+            print_json(&report);
+            return Ok(());
+        }
+
+        print!("{}", serde_yaml::to_string(&pack)?);
+
+        let folders = [("Overrides", &report.overrides), ("ClientOverrides", &report.client_overrides),
+                       ("ServerOverrides", &report.server_overrides)];
+        for (name, dirs) in folders.iter().filter(|(_, dirs)| !dirs.is_empty()) {
+            let dirs = dirs.iter().map(|d| d.display().to_string()).collect::<Vec<String>>();
+            println!("# {} applied in order: {}", name, dirs.join(", "));
+        }
+
+        Ok(())
     }
 
     pub async fn run() -> RResult<()> {
         let cf = CurseForge::new();
         let args: Self = argh::from_env();
-        
-        if args.switch.is_some() {
+        set_json_output(args.json);
+
+        if let Some(command) = &args.command {
+            match command {
+                Command::List(cmd) => args.list_fn(cmd, cf).await?,
+                Command::Info(cmd) => args.info_fn(cmd, cf).await?,
+                Command::Outdated(cmd) => args.outdated_fn(cmd, cf).await?,
+                Command::Tree(cmd) => args.tree_fn(cmd, cf).await?,
+                Command::Import(cmd) => args.import_fn(cmd, cf).await?,
+                Command::Export(cmd) => args.export_fn(cmd, cf).await?,
+                Command::Profile(cmd) => args.profile_fn(cmd).await?,
+                Command::Switch(cmd) => args.switch_fn(cmd)?,
+                Command::Status(_) => args.status_fn()?,
+                Command::ServerPack(cmd) => args.server_pack_fn(cmd, cf).await?,
+                Command::InstallLoader(cmd) => args.install_loader_fn(cmd).await?,
+                Command::Migrate(cmd) => args.migrate_fn(cmd, cf).await?,
+                Command::Check(cmd) => args.check_fn(cmd)?,
+                Command::Scan(cmd) => args.scan_fn(cmd)?,
+                Command::ShowEffective(cmd) => args.show_effective_fn(cmd)?
+            }
+        } else if args.switch.is_some() {
             args.switch_modpack();
         } else if args.remove.is_some() {
-            args.remove_modpack();
+            args.remove_modpack()?;
         } else if args.download.is_some() {
             args.download_fn(cf).await?;
         } else if args.create_modpack.is_some() {
@@ -1,27 +1,59 @@
 use {
     whoami::username,
     std::{
-        path::{PathBuf},
+        collections::BTreeMap,
+        path::{Path, PathBuf},
     },
     serde::{Serialize, Deserialize},
     symlink::{symlink_dir, remove_symlink_dir},
     colored::*,
 
-    crate::modpack::*
+    curseforge::prelude::*,
+    crate::{modpack::*, output::*, instance::*, inheritance::*, lock::ModpackLock}
 };
 
+// Modpack linked into minecraft directory by switch
+#[derive(Serialize)]
+pub struct SwitchReport {
+    pub modpack: String,
+    pub managed_dirs: Vec<String>,
+
+    // Modpack or backup of game directories that `switch --back` returns to
+    pub previous: Option<String>
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct McGetConfig {
     #[serde(rename = "MinecraftPath")]
     minecraft_path: PathBuf,
 
+    #[serde(rename = "LoaderUrls", default, skip_serializing_if = "LoaderUrls::is_default")]
+    loader_urls: LoaderUrls,
+
+    // `warn` (default) or `block` installation of jars flagged by malware scanner
+    #[serde(rename = "Scan", default, skip_serializing_if = "ScanMode::is_warn")]
+    scan: ScanMode,
+
     #[serde(skip)]
     modpacks: Vec<PathBuf>
 }
 
 impl McGetConfig {
     pub fn new(mc_path: String) -> McGetConfig {
-        McGetConfig{minecraft_path: mc_path.into(), modpacks: Default::default()}
+        McGetConfig{minecraft_path: mc_path.into(), loader_urls: Default::default(),
+                    scan: Default::default(), modpacks: Default::default()}
+    }
+
+    pub fn minecraft_path(&self) -> &Path {
+        &self.minecraft_path
+    }
+
+    pub fn loader_urls(&self) -> &LoaderUrls {
+        &self.loader_urls
+    }
+
+    pub fn scan_mode(&self) -> ScanMode {
+        self.scan
     }
 
     pub fn store(&self) {
@@ -57,29 +89,138 @@ impl McGetConfig {
         }
     }
 
-    pub fn switch_modpack(&self, name: &str) {
-        let pack = self.find_modpack(name);
+    // Name of modpack currently linked into minecraft directory
+    pub fn active_modpack(&self) -> Option<String> {
+        linked_modpack(&self.minecraft_path.join("mods"))
+    }
 
-        let mods = self.minecraft_path.join("mods");
-        match remove_symlink_dir(&mods) {
-            Ok(_) => {},
-            Err(e) => {
-                println!("{} Can't remove symlink for directory {}: {}", "!!WARNING".red(),
-                         mods.to_str().unwrap(), e.to_string().red());
+    // Switches modpack and remembers previously active one for `switch --back`
+    pub fn switch_modpack(&self, name: &str) -> SwitchReport {
+        self.find_modpack(name);
+        let mut history = SwitchHistory::load();
+        self.switch_to(name, &mut history)
+    }
+
+    // Switches to the last modpack from switch history
+    pub fn switch_back(&self) -> SwitchReport {
+        let mut history = SwitchHistory::load();
+        let name = match history.packs.pop() {
+            Some(name) => name,
+            None => {
+                println!("Error: {}", "Switch history is empty".red());
+                std::process::exit(1);
             }
+        };
+
+        if !pack_mods_dir(&name).exists() {
+            println!("Error: {} {}", "No pack named ".red(), name.bold());
+            std::process::exit(1);
         }
 
-        match symlink_dir(&pack, &mods) {
-            Ok(_) => {
-                println!("Created symlink {} => {}", pack.as_path().to_str().unwrap().bold(),
-                         mods.as_path().to_str().unwrap().bold());
+        self.switch_to(&name, &mut history)
+    }
+
+    // Moves real game directories into backup modpack, links `name` and puts modpack that was active
+    // (or the backup) into switch history, so switching back returns to it
+    fn switch_to(&self, name: &str, history: &mut SwitchHistory) -> SwitchReport {
+        let state = InstanceState::load(name);
+
+        let previous = match backup_dirs(&self.minecraft_path, &state.managed_dirs) {
+            Ok(Some(backup)) => {
+                print_message(format!("Moved existing game directories into backup modpack {}", backup.bold()));
+                Some(backup)
             },
+            Ok(None) => self.active_modpack(),
             Err(e) => {
-                println!("Can't symlink {} => {}; Reason: {}", pack.as_path().to_str().unwrap().bold(),
-                         mods.as_path().to_str().unwrap().bold(), e.to_string());
+                println!("Error: Failed to back up game directories: {}", e.to_string().red());
+                std::process::exit(1);
+            }
+        };
+
+        let previous = previous.filter(|p| p != name);
+        if let Some(previous) = &previous {
+            history.push(previous);
+        }
+
+        self.link_modpack(name, &state);
+        history.store().unwrap_or_default();
+
+        SwitchReport{modpack: name.to_string(), managed_dirs: state.managed_dirs, previous}
+    }
+
+    // Links every managed directory of modpack into minecraft directory. New links are created
+    // aside first and then renamed over the old ones, so nothing is changed if any of them fails
+    fn link_modpack(&self, name: &str, state: &InstanceState) {
+        let mut staged = vec![];
+        for dir in &state.managed_dirs {
+            let source = InstanceState::source(name, dir);
+            let temp = self.minecraft_path.join(format!(".mcget-{}", dir));
+            std::fs::create_dir_all(&source).unwrap_or_default();
+            remove_symlink_dir(&temp).unwrap_or_default();
+
+            if let Err(e) = symlink_dir(&source, &temp) {
+                println!("Can't symlink {} => {}; Reason: {}", source.to_string_lossy().bold(),
+                         temp.to_string_lossy().bold(), e);
+                for (temp, _) in &staged {
+                    remove_symlink_dir(temp).unwrap_or_default();
+                }
+                std::process::exit(1);
+            }
+
+            staged.push((temp, self.minecraft_path.join(dir)));
+        }
+
+        // Only links can be replaced, real directories are moved into backup modpack beforehand
+        for (_, target) in &staged {
+            if target.exists() && !is_symlink(target) {
+                println!("Error: {} {}", target.to_string_lossy().bold(), "is not a link, nothing is changed".red());
+                for (temp, _) in &staged {
+                    remove_symlink_dir(temp).unwrap_or_default();
+                }
                 std::process::exit(1);
             }
         }
+
+        let mut replaced: Vec<(&PathBuf, Option<PathBuf>)> = vec![];
+        for (i, (temp, target)) in staged.iter().enumerate() {
+            let previous = std::fs::read_link(target).ok();
+            let mut result = std::fs::rename(temp, target);
+            if result.is_err() {
+                remove_symlink_dir(target).unwrap_or_default();
+                result = std::fs::rename(temp, target);
+            }
+
+            if let Err(e) = result {
+                println!("Error: Can't replace {}: {}", target.to_string_lossy().bold(), e.to_string().red());
+
+                // Restores links of the previous modpack
+                replaced.push((target, previous));
+                for (target, previous) in &replaced {
+                    remove_symlink_dir(target).unwrap_or_default();
+                    if let Some(previous) = previous {
+                        symlink_dir(previous, target).unwrap_or_default();
+                    }
+                }
+                for (temp, _) in &staged[i..] {
+                    remove_symlink_dir(temp).unwrap_or_default();
+                }
+                std::process::exit(1);
+            }
+            replaced.push((target, previous));
+        }
+
+        // Links left from previous modpack that this one doesn't manage
+        let location = get_config_location();
+        for dir in DEFAULT_MANAGED_DIRS {
+            let target = self.minecraft_path.join(dir);
+            if state.managed_dirs.iter().any(|d| d == dir) || !is_symlink(&target) {
+                continue;
+            }
+
+            if std::fs::read_link(&target).map(|l| l.starts_with(&location)).unwrap_or(false) {
+                remove_symlink_dir(&target).unwrap_or_default();
+            }
+        }
     }
 
     pub fn lookup() -> McGetConfig {
@@ -92,7 +233,7 @@ impl McGetConfig {
         let content = std::fs::read_to_string(&path).unwrap();
         let mut cfg: McGetConfig = serde_yaml::from_str(&content).unwrap();
         
-        for dir in std::fs::read_dir(path.parent().unwrap().join("modpacks")).unwrap() {
+        for dir in std::fs::read_dir(path.parent().unwrap().join("modpacks")).into_iter().flatten() {
             let entry;
             match dir {
                 Ok(e) => { entry = e; },
@@ -108,12 +249,15 @@ impl McGetConfig {
 
 impl ModpackCfg {
     pub fn new(name: String, version: String,
-               loader: String, file: String) -> ModpackCfg {
+               loader: ModLoader, file: String) -> ModpackCfg {
         ModpackCfg{
             file,
+            inheritance: None,
             mc: MinecraftModpack{
-                name, version,
-                loader, mods: Default::default()
+                name, version, version_policy: Default::default(), loader, loader_version: None,
+                overrides: None, client_overrides: None, server_overrides: None,
+                managed_dirs: None, exclude: vec![], replace: vec![],
+                mods: Default::default(), resolved_version: None
             }
         }
     }
@@ -127,9 +271,40 @@ impl ModpackCfg {
             }
         };
 
-        match serde_yaml::from_str::<ModpackCfg>(&contents) {
+        let value: serde_yaml::Value = match serde_yaml::from_str(&contents) {
+            Ok(r) => r,
+            Err(e) => {
+                println!("Failed to parse YAML file: {}", e.to_string().red());
+                std::process::exit(1);
+            }
+        };
+
+        // Modpacks with parents are merged with them before parsing
+        let (value, inheritance) = match value.get("Minecraft").and_then(|m| m.as_mapping()) {
+            Some(own) if has_parents(own) => match inherit(filename, own) {
+                Ok((section, inheritance)) => {
+                    let mut value = serde_yaml::Mapping::new();
+                    value.insert("Minecraft".into(), serde_yaml::Value::Mapping(section));
+                    (serde_yaml::Value::Mapping(value), Some(inheritance))
+                },
+                Err(e) => {
+                    println!("{} {}", "Error:".red(), e);
+                    std::process::exit(1);
+                }
+            },
+            _ => (value, None)
+        };
+
+        let mods = value.get("Minecraft").and_then(|m| m.get("Mods")).and_then(|m| m.as_sequence()).cloned();
+        match serde_yaml::from_value::<ModpackCfg>(value) {
             Ok(mut r) => {
                 r.file = filename.to_string();
+                if let Some(inheritance) = &inheritance {
+                    for (mod_, entry) in r.mc.mods.iter_mut().zip(mods.iter().flatten()) {
+                        mod_.inherited = inheritance.is_inherited(entry);
+                    }
+                }
+                r.inheritance = inheritance;
                 r
             },
             Err(e) => {
@@ -139,12 +314,158 @@ impl ModpackCfg {
         }
     }
 
+    // Fills in and updates cached names of CurseForge mods, returns true if anything changed
+    pub async fn refresh_names(&mut self, cf: &CurseForge) -> bool {
+        let mut tasks = vec![];
+        for (idx, mod_) in self.mc.mods.iter().enumerate() {
+            let id = match mod_.id {
+                Some(id) => id,
+                None => { continue; }
+            };
+
+            let cf = cf.clone();
+            tasks.push(tokio::spawn(async move {
+                (idx, cf.mod_info(id).await.ok())
+            }));
+        }
+
+        let mut changed = false;
+        for task in tasks {
+            let (idx, info) = match task.await {
+                Ok((idx, Some(info))) => (idx, info),
+                _ => { continue; }
+            };
+
+            let mod_ = &mut self.mc.mods[idx];
+            if mod_.name.as_ref() != Some(&info.name) || mod_.slug.as_ref() != Some(&info.slug) {
+                mod_.set_names(&info);
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    // Path relative to the modpack file
+    pub fn pack_path(&self, relative: &str) -> PathBuf {
+        Path::new(&self.file).parent().unwrap_or_else(|| Path::new("")).join(relative)
+    }
+
+    // Override directories of parents followed by own one, later ones win
+    fn override_dirs(&self, inherited: fn(&OverrideDirs) -> &Option<PathBuf>, own: &Option<String>) -> Vec<PathBuf> {
+        let parents = self.inheritance.iter().flat_map(|i| &i.overrides).filter_map(|d| inherited(d).clone());
+        parents.chain(own.as_ref().map(|o| self.pack_path(o))).collect()
+    }
+
+    pub fn overrides_dirs(&self) -> Vec<PathBuf> {
+        self.override_dirs(|d| &d.overrides, &self.mc.overrides)
+    }
+
+    pub fn client_overrides_dirs(&self) -> Vec<PathBuf> {
+        self.override_dirs(|d| &d.client, &self.mc.client_overrides)
+    }
+
+    pub fn server_overrides_dirs(&self) -> Vec<PathBuf> {
+        self.override_dirs(|d| &d.server, &self.mc.server_overrides)
+    }
+
+    // Download target of entry not hosted on CurseForge, installed into `dest`,
+    // already downloaded file is kept if it matches the `lock`
+    pub fn direct_target(&self, mod_: &ModpackMod, dest: String,
+                         lock: Option<&ModpackLock>) -> Option<DownloadTarget> {
+        let mut target = match (&mod_.url, &mod_.path) {
+            (Some(url), _) => {
+                let mut target = DownloadTarget::with_url(url.clone(), dest);
+                let path = format!("{}/{}", mod_.kind.dir(), url_filename(url));
+                target.expected = lock.map(|l| l.expected(&path)).unwrap_or_default();
+                target
+            },
+            (None, Some(path)) => DownloadTarget::with_path(self.pack_path(path).to_string_lossy().to_string(), dest),
+            (None, None) => { return None; }
+        };
+        target.required = !mod_.optional;
+        Some(target)
+    }
+
+    // Entry a local file of download report was installed from
+    pub fn find_source(&self, source: &str) -> Option<&ModpackMod> {
+        self.mc.mods.iter().find(|m| m.path.as_ref().map(|p| self.pack_path(p) == Path::new(source)).unwrap_or(false))
+    }
+
+    // Lock file is stored next to the modpack file: mcpack.yaml => mcpack.lock
+    pub fn lock_file(&self) -> String {
+        Path::new(&self.file).with_extension("lock").to_string_lossy().to_string()
+    }
+
+    // Modpacks with parents keep only their own entries and values
     pub fn store(&self) {
-        let content = serde_yaml::to_string(self).unwrap();
+        let content = match &self.inheritance {
+            Some(inheritance) => {
+                let current = serde_yaml::to_value(&self.mc).unwrap();
+                // Inherited entries changed since loading are written as overrides
+                let own_mods = self.mc.mods.iter()
+                    .map(|m| (m, serde_yaml::to_value(m).unwrap()))
+                    .filter(|(m, entry)| !m.inherited || inheritance.is_changed(entry))
+                    .map(|(_, entry)| entry).collect();
+                let mut value = serde_yaml::Mapping::new();
+                value.insert("Minecraft".into(), serde_yaml::Value::Mapping(
+                    inheritance.own_section(current.as_mapping().unwrap(), own_mods)));
+                serde_yaml::to_string(&value).unwrap()
+            },
+            None => serde_yaml::to_string(self).unwrap()
+        };
         std::fs::write(&self.file, content).unwrap_or_default();
     }
 }
 
+pub fn is_symlink(path: &Path) -> bool {
+    std::fs::symlink_metadata(path).map(|m| m.file_type().is_symlink()).unwrap_or(false)
+}
+
+// Collects paths of all files under `dir` relative to it, prefixed with `prefix`
+pub fn list_files(dir: &Path, prefix: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let relative = prefix.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            list_files(&entry.path(), &relative, out)?;
+        } else {
+            out.push(relative);
+        }
+    }
+
+    Ok(())
+}
+
+// Files of directories by their relative path, files of later directories win
+pub fn merged_files(dirs: &[PathBuf]) -> std::io::Result<BTreeMap<String, PathBuf>> {
+    let mut merged = BTreeMap::new();
+    for dir in dirs {
+        let mut files = vec![];
+        list_files(dir, Path::new(""), &mut files)?;
+        for file in files {
+            merged.insert(file.to_string_lossy().replace('\\', "/"), dir.join(&file));
+        }
+    }
+
+    Ok(merged)
+}
+
+pub fn copy_dir(src: &Path, dst: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let target = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+
+    Ok(())
+}
+
 fn default_minecraft_path() -> String {
     let username = username();
     if cfg!(target_os = "linux") || cfg!(target_os = "freebsd")
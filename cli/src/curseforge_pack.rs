@@ -0,0 +1,383 @@
+use {
+    std::{
+        fs::File,
+        io::{Read, Write},
+        path::{Path, PathBuf}
+    },
+    serde::{Serialize, Deserialize},
+    zip::{ZipArchive, ZipWriter, write::FileOptions},
+
+    curseforge::prelude::*,
+    crate::{modpack::*, config::merged_files, instance::scan_overrides}
+};
+
+// CurseForge modpack `manifest.json`
+#[derive(Serialize, Deserialize)]
+pub struct CurseForgeManifest {
+    pub minecraft: ManifestMinecraft,
+
+    #[serde(rename = "manifestType")]
+    pub manifest_type: String,
+
+    #[serde(rename = "manifestVersion")]
+    pub manifest_version: usize,
+
+    pub name: String,
+
+    #[serde(default)]
+    pub version: String,
+
+    #[serde(default)]
+    pub author: String,
+
+    pub files: Vec<ManifestFile>,
+
+    #[serde(default = "default_overrides")]
+    pub overrides: String
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ManifestMinecraft {
+    pub version: String,
+
+    #[serde(rename = "modLoaders")]
+    pub mod_loaders: Vec<ManifestModLoader>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ManifestModLoader {
+    // Loader with version, e.g. forge-47.2.0
+    pub id: String,
+
+    #[serde(default)]
+    pub primary: bool
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ManifestFile {
+    #[serde(rename = "projectID")]
+    pub project_id: usize,
+
+    #[serde(rename = "fileID")]
+    pub file_id: usize,
+
+    #[serde(default = "default_required")]
+    pub required: bool
+}
+
+fn default_overrides() -> String {
+    "overrides".to_string()
+}
+
+fn default_required() -> bool {
+    true
+}
+
+impl CurseForgeManifest {
+    // Primary mod loader as (loader, loader version)
+    pub fn primary_loader(&self) -> Option<(ModLoader, String)> {
+        let loader = self.minecraft.mod_loaders.iter().find(
+            |l| l.primary
+        ).or_else(|| self.minecraft.mod_loaders.first())?;
+
+        Some(match loader.id.split_once('-') {
+            Some((name, version)) => (ModLoader::parse(name), version.to_string()),
+            None => (ModLoader::parse(&loader.id), String::new())
+        })
+    }
+
+    pub fn to_modpack(&self, file: String) -> ModpackCfg {
+        let loader = self.primary_loader().map(|l| l.0).unwrap_or_default();
+        let mut pack = ModpackCfg::new(self.name.clone(), self.minecraft.version.clone(),
+                                       loader, file);
+        pack.mc.loader_version = self.primary_loader().map(|l| l.1).filter(|v| !v.is_empty());
+
+        for file in &self.files {
+            let mut mod_ = ModpackMod::with_id(file.project_id);
+            mod_.file = Some(file.file_id);
+            mod_.optional = !file.required;
+
+            pack.mc.mods.push(mod_);
+        }
+
+        pack
+    }
+}
+
+// Pack name usable as a file name
+pub fn sanitize_pack_name(name: &str) -> String {
+    name.chars().map(
+        |c| if c == '/' || c == '\\' || c == ':' { '-' } else { c }
+    ).collect::<String>().trim().to_string()
+}
+
+// Extracts entries under `prefix` of zip archive into `dest`, returns number of extracted files
+pub fn extract_zip_dir(archive: &mut ZipArchive<File>, prefix: &str,
+                       dest: &Path) -> RResult<usize> {
+    let prefix = Path::new(prefix);
+    let mut extracted = 0usize;
+
+    for idx in 0..archive.len() {
+        let mut entry = archive.by_index(idx)?;
+        let relative = match entry.enclosed_name().and_then(|n| n.strip_prefix(prefix).ok()) {
+            Some(r) if r.components().count() > 0 => r.to_path_buf(),
+            _ => { continue; }
+        };
+
+        let target = dest.join(relative);
+        if entry.is_dir() {
+            std::fs::create_dir_all(&target)?;
+            continue;
+        }
+
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut contents = vec![];
+        entry.read_to_end(&mut contents)?;
+        std::fs::write(&target, contents)?;
+        extracted += 1;
+    }
+
+    Ok(extracted)
+}
+
+// Reads CurseForge modpack zip into pinned modpack stored at `output`,
+// `overrides` directory is extracted next to it and its jars are scanned
+pub fn import_curseforge(zip: &str, output: Option<&String>, scan: ScanMode) -> RResult<ModpackCfg> {
+    let mut archive = ZipArchive::new(File::open(zip)?)?;
+
+    let manifest: CurseForgeManifest = {
+        let mut entry = archive.by_name("manifest.json")
+            .map_err(|_| format!("{} has no manifest.json", zip))?;
+        let mut contents = String::new();
+        entry.read_to_string(&mut contents)?;
+
+        serde_json::from_str(&contents)?
+    };
+
+    let output = match output {
+        Some(o) => PathBuf::from(o),
+        None => PathBuf::from(sanitize_pack_name(&manifest.name) + ".yaml")
+    };
+    let mut pack = manifest.to_modpack(output.to_string_lossy().to_string());
+    pack.mc.name = sanitize_pack_name(&pack.mc.name);
+
+    let overrides = format!("{}-overrides", output.file_stem().unwrap().to_string_lossy());
+    let overrides_dir = output.parent().unwrap_or_else(|| Path::new("")).join(&overrides);
+    if extract_zip_dir(&mut archive, &manifest.overrides, &overrides_dir)? > 0 {
+        scan_overrides(&overrides_dir, scan)?;
+        pack.mc.overrides = Some(overrides);
+    }
+
+    pack.store();
+
+    Ok(pack)
+}
+
+pub struct CurseForgeExport {
+    pub output: PathBuf,
+    pub loader_version: String,
+
+    pub version: String,
+    pub author: String,
+
+    // Put non-CurseForge mods into overrides and pin unpinned mods to their latest files
+    pub bundle_foreign: bool
+}
+
+pub fn modlist_html(pack: &ModpackCfg) -> String {
+    let mut html = "<ul>\n".to_string();
+    for mod_ in pack.mc.mods.iter().filter(|m| m.id.is_some()) {
+        let link = match &mod_.slug {
+            Some(slug) => format!("https://www.curseforge.com/minecraft/{}/{}",
+                                  mod_.kind.url_section(), slug),
+            None => format!("https://www.curseforge.com/projects/{}", mod_.id.unwrap())
+        };
+        html.push_str(&format!("<li><a href=\"{}\">{}</a></li>\n", link,
+                               html_escape(&mod_.display_name())));
+    }
+    html.push_str("</ul>\n");
+
+    html
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// Writes files of override directories under `prefix`, files of later directories win
+pub fn zip_dirs<W: Write + std::io::Seek>(zip: &mut ZipWriter<W>, dirs: &[PathBuf],
+                                         prefix: &str) -> RResult<()> {
+    for (relative, file) in merged_files(dirs)? {
+        zip.start_file(format!("{}/{}", prefix, relative), FileOptions::default())?;
+        zip.write_all(&std::fs::read(file)?)?;
+    }
+
+    Ok(())
+}
+
+// Pins required dependencies missing from manifest files, CurseForge launcher doesn't resolve them itself
+async fn pin_dependencies(pack: &ModpackCfg, cf: &CurseForge, files: &mut Vec<ManifestFile>) -> RResult<()> {
+    let roots = files.iter()
+        .filter(|f| pack.mc.kind_of(f.project_id).is_mod())
+        .map(|f| (f.project_id, Some(f.file_id)))
+        .collect();
+    let game = pack.mc.game_version();
+    let deps = resolve_dependencies(cf, roots, game.clone(), String::new(), true,
+                                    &pack.mc.resolve_rules()).await;
+
+    let mut tasks = vec![];
+    for id in deps.targets.iter().filter_map(|t| t.id) {
+        if files.iter().any(|f| f.project_id == id) {
+            continue;
+        }
+
+        let cf = cf.clone();
+        let game = game.clone();
+        tasks.push(tokio::spawn(async move {
+            (id, cf.select_file(id, None, game).await.ok().flatten())
+        }));
+    }
+
+    let mut missing = vec![];
+    for task in tasks {
+        match task.await? {
+            (id, Some(file)) => files.push(ManifestFile{project_id: id, file_id: file.id, required: true}),
+            (id, None) => missing.push(id.to_string())
+        }
+    }
+
+    if !missing.is_empty() {
+        return Err(format!("dependencies {} have no file for {}", missing.join(", "), pack.mc.version).into());
+    }
+
+    Ok(())
+}
+
+// Writes zip with manifest, overrides and `bundled` files as (path inside of overrides, file)
+fn write_curseforge_zip(pack: &ModpackCfg, manifest: &CurseForgeManifest, bundled: &[(String, PathBuf)],
+                        output: &Path) -> RResult<()> {
+    let mut zip = ZipWriter::new(File::create(output)?);
+    zip.start_file("manifest.json", FileOptions::default())?;
+    zip.write_all(serde_json::to_string_pretty(manifest)?.as_bytes())?;
+
+    zip.start_file("modlist.html", FileOptions::default())?;
+    zip.write_all(modlist_html(pack).as_bytes())?;
+
+    zip_dirs(&mut zip, &pack.overrides_dirs(), &manifest.overrides)?;
+
+    for (path, source) in bundled {
+        let data = std::fs::read(source).map_err(|e| format!("can't read {}: {}", source.display(), e))?;
+        zip.start_file(format!("{}/{}", manifest.overrides, path), FileOptions::default())?;
+        zip.write_all(&data)?;
+    }
+
+    zip.finish()?;
+
+    Ok(())
+}
+
+// Downloads foreign files into `temp`, returns them as (path inside of overrides, file)
+async fn download_foreign(cf: &CurseForge, game: GameVersion, foreign: Vec<(String, ProjectKind)>,
+                          temp: &Path, scan: ScanMode) -> RResult<Vec<(String, PathBuf)>> {
+    std::fs::create_dir_all(temp)?;
+
+    let mut downloader = MassDownloader::new().with_quiet(true).with_scan(scan);
+    for (url, kind) in foreign {
+        downloader.add_target(DownloadTarget::with_url(url, temp.to_string_lossy().to_string())
+            .with_kind(kind, temp));
+    }
+
+    let report = downloader.download(cf, game).await;
+    if let Some(failed) = report.failed().next() {
+        return Err(format!("failed to download {}", failed.name()).into());
+    }
+
+    Ok(report.entries.iter().map(|entry| {
+        let path = PathBuf::from(entry.path.as_ref().unwrap());
+        (format!("{}/{}", entry.kind.dir(), path.file_name().unwrap().to_string_lossy()), path)
+    }).collect())
+}
+
+// Writes modpack as CurseForge modpack zip, nothing is left at output if it fails
+pub async fn export_curseforge(pack: &ModpackCfg, cf: &CurseForge,
+                               opts: &CurseForgeExport, scan: ScanMode) -> RResult<()> {
+    let game = pack.mc.game_version();
+    let mut files = vec![];
+    let mut foreign = vec![];
+    let mut bundled = vec![];
+    let mut errors = vec![];
+
+    for mod_ in &pack.mc.mods {
+        match (mod_.id, mod_.file, &mod_.url) {
+            (Some(id), Some(file), _) => {
+                files.push(ManifestFile{project_id: id, file_id: file, required: !mod_.optional});
+            },
+            (Some(id), None, _) if opts.bundle_foreign => {
+                match cf.select_file(id, None, game.clone().for_kind(mod_.kind)).await? {
+                    Some(file) => files.push(ManifestFile{project_id: id, file_id: file.id,
+                                                          required: !mod_.optional}),
+                    None => errors.push(format!("{} has no file for {}", mod_.display_name(), pack.mc.version))
+                }
+            },
+            (Some(_), None, _) => {
+                errors.push(format!("{} is not pinned", mod_.display_name()));
+            },
+            (None, _, Some(url)) if opts.bundle_foreign => {
+                foreign.push((url.clone(), mod_.kind));
+            },
+            // Local files can't come from anywhere else
+            (None, _, None) if mod_.path.is_some() => {
+                let source = pack.pack_path(mod_.path.as_ref().unwrap());
+                bundled.push((format!("{}/{}", mod_.kind.dir(), source.file_name().unwrap().to_string_lossy()), source));
+            },
+            _ => {
+                errors.push(format!("{} is not from CurseForge", mod_.display_name()));
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(format!("can't export {}: {}", pack.mc.name, errors.join(", ")).into());
+    }
+    pin_dependencies(pack, cf, &mut files).await?;
+
+    let manifest = CurseForgeManifest{
+        minecraft: ManifestMinecraft{
+            version: pack.mc.exact_version()?,
+            mod_loaders: vec![ManifestModLoader{
+                id: format!("{}-{}", pack.mc.loader, opts.loader_version),
+                primary: true
+            }]
+        },
+        manifest_type: "minecraftModpack".to_string(),
+        manifest_version: 1,
+        name: pack.mc.name.clone(),
+        version: opts.version.clone(),
+        author: opts.author.clone(),
+        files,
+        overrides: default_overrides()
+    };
+
+    // Fresh directory every time, downloader keeps files it finds there
+    let temp = std::env::temp_dir().join(format!("mcget-export-{}-{}", sanitize_pack_name(&pack.mc.name),
+                                                 std::process::id()));
+    std::fs::remove_dir_all(&temp).unwrap_or_default();
+
+    let result = match download_foreign(cf, game, foreign, &temp, scan).await {
+        Ok(downloaded) => {
+            bundled.extend(downloaded);
+            write_curseforge_zip(pack, &manifest, &bundled, &opts.output)
+        },
+        Err(e) => Err(e)
+    };
+
+    std::fs::remove_dir_all(&temp).unwrap_or_default();
+    if result.is_err() {
+        std::fs::remove_file(&opts.output).unwrap_or_default();
+    }
+
+    result
+}
@@ -0,0 +1,223 @@
+use {
+    std::{collections::HashMap, path::{Path, PathBuf}},
+    colored::*,
+
+    curseforge::prelude::*,
+    crate::{modpack::*, lock::*, output::*, curseforge_pack::sanitize_pack_name}
+};
+
+// Jar of imported directory with its hashes
+pub struct ScannedJar {
+    pub path: PathBuf,
+    name: String,
+    size: usize,
+    fingerprint: u32,
+    sha1: String,
+    sha512: String,
+    meta: Option<JarMeta>
+}
+
+pub fn scan_jars(dir: &Path) -> RResult<Vec<ScannedJar>> {
+    let mut jars = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() || path.extension().map(|e| e != "jar").unwrap_or(true) {
+            continue;
+        }
+
+        let data = std::fs::read(&path)?;
+        jars.push(ScannedJar{
+            name: path.file_name().unwrap().to_string_lossy().to_string(),
+            size: data.len(),
+            fingerprint: curseforge_fingerprint(&data),
+            sha1: sha1_hex(&data),
+            sha512: sha512_hex(&data),
+            meta: JarMeta::read(&path),
+            path
+        });
+    }
+    jars.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(jars)
+}
+
+// Most common value, the first one seen wins ties
+fn most_common<T: PartialEq>(values: impl Iterator<Item = T>) -> Option<T> {
+    let mut counts: Vec<(T, usize)> = vec![];
+    for value in values {
+        match counts.iter_mut().find(|(v, _)| *v == value) {
+            Some((_, count)) => { *count += 1; },
+            None => counts.push((value, 1))
+        }
+    }
+
+    let max = counts.iter().map(|(_, count)| *count).max()?;
+    counts.into_iter().find(|(_, count)| *count == max).map(|(value, _)| value)
+}
+
+// Loader and minecraft version most jars are made for
+fn detect_game(jars: &[ScannedJar]) -> (Option<ModLoader>, Option<String>) {
+    let loader = most_common(jars.iter().filter_map(|j| Some(j.meta.as_ref()?.metadata.first()?.loader())));
+    let version = loader.as_ref().and_then(|loader| most_common(jars.iter().filter_map(|j| {
+        let range = j.meta.as_ref()?.for_loader(loader)?.minecraft()?;
+        range.as_exact().or_else(|| range.minimum().map(|m| m.0)).map(|v| v.to_string())
+    })));
+
+    (loader, version)
+}
+
+fn print_match(jar: &ScannedJar, source: &str) {
+    if !json_output() {
+        println!("{} {} on {}", "Matched".green(), jar.name.bold(), source);
+    }
+}
+
+// Adds `jars` to pinned modpack: they are identified by CurseForge fingerprints and Modrinth hashes,
+// unknown ones are copied next to the modpack and added as `Path` entries
+pub async fn import_jars(pack: &mut ModpackCfg, lock: &mut ModpackLock, jars: &[ScannedJar],
+                         cf: &CurseForge) -> RResult<()> {
+    let fingerprints: Vec<u32> = jars.iter().map(|j| j.fingerprint).collect();
+    let curseforge = match cf.fingerprints(&fingerprints).await {
+        Ok(matches) => matches,
+        Err(e) => {
+            print_warning(format!("CurseForge lookup failed: {}", e));
+            vec![]
+        }
+    };
+
+    let mut unmatched = vec![];
+    for jar in jars {
+        let found = curseforge.iter().find(|m| m.file.fingerprint == Some(jar.fingerprint));
+        let found = match found {
+            Some(found) => found,
+            None => {
+                unmatched.push(jar);
+                continue;
+            }
+        };
+
+        print_match(jar, "CurseForge");
+        let mut mod_ = ModpackMod::with_id(found.id);
+        mod_.file = Some(found.file.id);
+        pack.mc.mods.push(mod_);
+
+        lock.files.push(LockedFile{
+            id: Some(found.id), file: Some(found.file.id),
+            url: found.file.download_url.clone(), source: None,
+            path: format!("mods/{}", found.file.filename),
+            size: jar.size, sha1: jar.sha1.clone(), sha512: jar.sha512.clone()
+        });
+    }
+
+    let hashes: Vec<String> = unmatched.iter().map(|j| j.sha512.clone()).collect();
+    let api = Modrinth::new();
+    let modrinth = if unmatched.is_empty() {
+        HashMap::new()
+    } else {
+        api.version_files(&hashes, "sha512").await.unwrap_or_else(|e| {
+            print_warning(format!("Modrinth lookup failed: {}", e));
+            HashMap::new()
+        })
+    };
+
+    // Projects say on which side their files are needed
+    let project_ids: Vec<String> = modrinth.values().map(|v| v.project_id.clone()).collect();
+    let projects = if project_ids.is_empty() {
+        vec![]
+    } else {
+        api.projects(&project_ids).await.unwrap_or_else(|e| {
+            print_warning(format!("Modrinth project lookup failed: {}", e));
+            vec![]
+        })
+    };
+
+    let mut local = vec![];
+    for jar in unmatched {
+        let file = match modrinth.get(&jar.sha512).and_then(|v| v.file_by_sha512(&jar.sha512)) {
+            Some(file) => file,
+            None => {
+                local.push(jar);
+                continue;
+            }
+        };
+
+        print_match(jar, "Modrinth");
+        let mut mod_ = ModpackMod::with_url(file.url.clone());
+        mod_.side = modrinth.get(&jar.sha512)
+            .and_then(|v| projects.iter().find(|p| p.id == v.project_id))
+            .map(|p| p.side())
+            .filter(|s| *s != Side::Both);
+        pack.mc.mods.push(mod_);
+        lock.files.push(LockedFile{
+            id: None, file: None, url: file.url.clone(), source: None,
+            path: format!("mods/{}", file.filename),
+            size: jar.size, sha1: jar.sha1.clone(), sha512: jar.sha512.clone()
+        });
+    }
+
+    if !local.is_empty() {
+        let local_name = format!("{}-local", Path::new(&pack.file).file_stem().unwrap().to_string_lossy());
+        let local_dir = pack.pack_path(&local_name);
+        std::fs::create_dir_all(&local_dir)?;
+
+        for jar in &local {
+            std::fs::copy(&jar.path, local_dir.join(&jar.name))?;
+            if !json_output() {
+                println!("{} {} into {}", "Vendored".yellow(), jar.name.bold(), local_name);
+            }
+
+            let source = format!("{}/{}", local_name, jar.name);
+            pack.mc.mods.push(ModpackMod::with_path(source.clone()));
+            lock.files.push(LockedFile{
+                id: None, file: None, url: String::new(), source: Some(source),
+                path: format!("mods/{}", jar.name),
+                size: jar.size, sha1: jar.sha1.clone(), sha512: jar.sha512.clone()
+            });
+        }
+    }
+
+    pack.refresh_names(cf).await;
+    lock.files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(())
+}
+
+// Creates pinned modpack from existing mods directory, see `import_jars`
+pub async fn import_dir(dir: &str, output: Option<&String>, version: Option<&String>,
+                        loader: Option<&ModLoader>, cf: &CurseForge) -> RResult<ModpackCfg> {
+    let dir = Path::new(dir);
+    let jars = scan_jars(dir)?;
+    if jars.is_empty() {
+        return Err(format!("{} has no jars", dir.display()).into());
+    }
+
+    // `.minecraft/mods` is named after the instance
+    let dir_name = match dir.canonicalize()?.file_name() {
+        Some(name) if name == "mods" => dir.canonicalize()?.parent().and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().to_string()),
+        name => name.map(|n| n.to_string_lossy().to_string())
+    }.unwrap_or_else(|| "imported".to_string());
+    let name = match output {
+        Some(o) => Path::new(o).file_stem().unwrap().to_string_lossy().to_string(),
+        None => dir_name
+    };
+    let output = match output {
+        Some(o) => PathBuf::from(o),
+        None => PathBuf::from(sanitize_pack_name(&name) + ".yaml")
+    };
+
+    let (detected_loader, detected_version) = detect_game(&jars);
+    let loader = loader.cloned().or(detected_loader).unwrap_or_default();
+    let version = version.cloned().or(detected_version)
+        .ok_or(format!("can't detect minecraft version of {}, pass it with --version", dir.display()))?;
+
+    let mut pack = ModpackCfg::new(sanitize_pack_name(&name), version, loader,
+                                   output.to_string_lossy().to_string());
+    let mut lock = ModpackLock::new(pack.lock_file());
+    import_jars(&mut pack, &mut lock, &jars, cf).await?;
+
+    pack.store();
+    lock.store();
+
+    Ok(pack)
+}
@@ -0,0 +1,438 @@
+use {
+    std::path::{Path, PathBuf},
+    serde_yaml::{Mapping, Value},
+
+    curseforge::prelude::*,
+    crate::{output::*, modpack::ModpackMod}
+};
+
+// Where modpack file is read from
+#[derive(Clone)]
+enum PackLocation {
+    File(PathBuf),
+    Url(String)
+}
+
+impl PackLocation {
+    // Location of `reference` written in the modpack at this location
+    fn join(&self, reference: &str) -> PackLocation {
+        if is_url(reference) {
+            return PackLocation::Url(reference.to_string());
+        }
+
+        match self {
+            PackLocation::File(path) => PackLocation::File(parent_dir(path).join(reference)),
+            PackLocation::Url(url) => PackLocation::Url(format!("{}{}", &url[..url.rfind('/').map(|p| p + 1).unwrap_or(0)],
+                                                                reference.trim_start_matches("./")))
+        }
+    }
+
+    // Path or URL the modpack is identified by in cycle detection
+    fn key(&self) -> String {
+        match self {
+            PackLocation::File(path) => path.canonicalize().unwrap_or_else(|_| path.clone()).to_string_lossy().to_string(),
+            PackLocation::Url(url) => url.clone()
+        }
+    }
+
+    fn read(&self) -> RResult<String> {
+        match self {
+            PackLocation::File(path) => std::fs::read_to_string(path)
+                .map_err(|e| format!("can't read {}: {}", path.display(), e).into()),
+            PackLocation::Url(url) => {
+                let data = tokio::task::block_in_place(|| {
+                    tokio::runtime::Handle::current().block_on(fetch_url(url))
+                }).map_err(|e| format!("can't download {}: {}", url, e))?;
+                Ok(String::from_utf8_lossy(&data).to_string())
+            }
+        }
+    }
+
+    fn display(&self) -> String {
+        match self {
+            PackLocation::File(path) => path.display().to_string(),
+            PackLocation::Url(url) => url.clone()
+        }
+    }
+}
+
+fn is_url(reference: &str) -> bool {
+    reference.starts_with("http://") || reference.starts_with("https://")
+}
+
+fn parent_dir(path: &Path) -> PathBuf {
+    let parent = path.parent().unwrap_or_else(|| Path::new(""));
+    std::env::current_dir().map(|cwd| cwd.join(parent)).unwrap_or_else(|_| parent.to_path_buf())
+}
+
+// Override directories of one modpack of the inheritance chain
+#[derive(Default, Clone)]
+pub struct OverrideDirs {
+    // Modpack the directories belong to, it's reached several times by diamond includes
+    key: String,
+
+    pub overrides: Option<PathBuf>,
+    pub client: Option<PathBuf>,
+    pub server: Option<PathBuf>
+}
+
+// What modpack inherits from modpacks it extends and includes
+#[derive(Default, Clone)]
+pub struct Inheritance {
+    // `Minecraft` section merged from parents
+    pub base: Mapping,
+
+    // Own `Minecraft` section as written in the modpack file
+    pub own: Mapping,
+
+    // Override directories of parents, applied in this order before own ones
+    pub overrides: Vec<OverrideDirs>
+}
+
+fn key(name: &str) -> Value {
+    Value::String(name.to_string())
+}
+
+// Modpacks `section` extends and includes, in merge order
+fn parent_refs(section: &Mapping) -> Vec<String> {
+    let extends = section.get(&key("Extends")).and_then(|e| e.as_str()).map(|e| e.to_string());
+    let include = section.get(&key("Include")).and_then(|i| i.as_sequence()).into_iter().flatten()
+        .filter_map(|i| i.as_str().map(|i| i.to_string()));
+
+    extends.into_iter().chain(include).collect()
+}
+
+pub fn has_parents(section: &Mapping) -> bool {
+    !parent_refs(section).is_empty()
+}
+
+// Id, Url or Path identifying modpack entry
+fn mod_identity(entry: &Value) -> Option<(&'static str, Value)> {
+    ["Id", "Url", "Path"].into_iter().find_map(|name| match entry.get(name) {
+        Some(Value::Null) | None => None,
+        Some(value) => Some((name, value.clone()))
+    })
+}
+
+fn sequence(section: &Mapping, name: &str) -> Vec<Value> {
+    section.get(&key(name)).and_then(|v| v.as_sequence()).cloned().unwrap_or_default()
+}
+
+// `own` on top of `base`: mods with the same Id, Url or Path and replacements of the same mod
+// are taken from `own`, exclusions are united and drop excluded mods of `base`
+fn merge(base: &Mapping, own: &Mapping) -> Mapping {
+    let mut result = base.clone();
+    let excluded = sequence(own, "Exclude");
+
+    let mut mods: Vec<Value> = sequence(base, "Mods").into_iter()
+        .filter(|m| !excluded.iter().any(|e| m.get("Id") == Some(e)))
+        .collect();
+    for entry in sequence(own, "Mods") {
+        let identity = mod_identity(&entry);
+        match mods.iter_mut().find(|m| identity.is_some() && mod_identity(m) == identity) {
+            Some(inherited) => { *inherited = entry; },
+            None => mods.push(entry)
+        }
+    }
+
+    let mut exclude = sequence(base, "Exclude");
+    for id in excluded {
+        if !exclude.contains(&id) {
+            exclude.push(id);
+        }
+    }
+
+    let own_replace = sequence(own, "Replace");
+    let mut replace: Vec<Value> = sequence(base, "Replace").into_iter()
+        .filter(|r| !own_replace.iter().any(|o| o.get("From") == r.get("From")))
+        .collect();
+    replace.extend(own_replace);
+
+    for (name, value) in own {
+        result.insert(name.clone(), value.clone());
+    }
+    for (name, values) in [("Mods", mods), ("Exclude", exclude), ("Replace", replace)] {
+        if values.is_empty() && name != "Mods" {
+            result.remove(&key(name));
+        } else {
+            result.insert(key(name), Value::Sequence(values));
+        }
+    }
+    result.remove(&key("Extends"));
+    result.remove(&key("Include"));
+
+    result
+}
+
+// Makes paths of parent modpack usable from any other modpack: local ones become absolute,
+// ones of remote modpack become URLs
+fn rebase(section: &mut Mapping, location: &PackLocation) -> OverrideDirs {
+    let path_entry = |path: &str| match location {
+        PackLocation::File(_) => ("Path", Value::String(location.join(path).display())),
+        PackLocation::Url(_) => ("Url", Value::String(location.join(path).display()))
+    };
+
+    let mut mods = sequence(section, "Mods");
+    for entry in mods.iter_mut() {
+        let path = match entry.get("Path").and_then(|p| p.as_str()) {
+            Some(path) => path.to_string(),
+            None => { continue; }
+        };
+
+        if let Value::Mapping(entry) = entry {
+            entry.remove(&key("Path"));
+            let (name, value) = path_entry(&path);
+            entry.insert(key(name), value);
+        }
+    }
+
+    section.insert(key("Mods"), Value::Sequence(mods));
+
+    let mut dir = |name: &str| {
+        let relative = section.remove(&key(name))?.as_str()?.to_string();
+        match location {
+            PackLocation::File(_) => Some(PathBuf::from(location.join(&relative).display())),
+            PackLocation::Url(url) => {
+                print_warning(format!("{} of remote modpack {} can't be used", name, url));
+                None
+            }
+        }
+    };
+
+    OverrideDirs{key: location.key(), overrides: dir("Overrides"), client: dir("ClientOverrides"),
+                 server: dir("ServerOverrides")}
+}
+
+// `Minecraft` section of modpack at `location` merged with all of its parents,
+// `stack` holds modpacks being loaded to detect cycles
+fn effective_section(location: &PackLocation, stack: &mut Vec<String>) -> RResult<(Mapping, Vec<OverrideDirs>)> {
+    let id = location.key();
+    if let Some(start) = stack.iter().position(|s| *s == id) {
+        let cycle = stack[start..].iter().chain(std::iter::once(&id)).cloned().collect::<Vec<String>>();
+        return Err(format!("modpack inheritance cycle: {}", cycle.join(" -> ")).into());
+    }
+
+    let contents: Value = serde_yaml::from_str(&location.read()?)
+        .map_err(|e| format!("can't parse {}: {}", location.display(), e))?;
+    let mut section = match contents.get("Minecraft") {
+        Some(Value::Mapping(section)) => section.clone(),
+        _ => { return Err(format!("{} has no Minecraft section", location.display()).into()); }
+    };
+
+    stack.push(id);
+    let (base, mut overrides) = merged_parents(location, &section, stack)?;
+    stack.pop();
+
+    overrides.push(rebase(&mut section, location));
+    Ok((merge(&base, &section), overrides))
+}
+
+// `Exclude` drops inherited mods by CurseForge id only, anything else would be silently ignored
+fn check_exclude(section: &Mapping, location: &PackLocation) -> RResult<()> {
+    match sequence(section, "Exclude").iter().find(|e| e.as_u64().is_none()) {
+        Some(value) => Err(format!("Exclude of {} lists {}, only CurseForge mod ids can be excluded",
+                                   location.display(), serde_yaml::to_string(value)?.trim_start_matches("---").trim()).into()),
+        None => Ok(())
+    }
+}
+
+fn merged_parents(location: &PackLocation, section: &Mapping,
+                  stack: &mut Vec<String>) -> RResult<(Mapping, Vec<OverrideDirs>)> {
+    check_exclude(section, location)?;
+
+    let mut base = Mapping::new();
+    let mut overrides = vec![];
+    for reference in parent_refs(section) {
+        let (parent, dirs) = effective_section(&location.join(&reference), stack)?;
+        base = merge(&base, &parent);
+        for dirs in dirs {
+            if !overrides.iter().any(|o: &OverrideDirs| o.key == dirs.key) {
+                overrides.push(dirs);
+            }
+        }
+    }
+
+    Ok((base, overrides))
+}
+
+// Effective `Minecraft` section of modpack file with `own` section, together with what it inherits
+pub fn inherit(file: &str, own: &Mapping) -> RResult<(Mapping, Inheritance)> {
+    let location = PackLocation::File(PathBuf::from(file));
+    let mut stack = vec![location.key()];
+    let (base, overrides) = merged_parents(&location, own, &mut stack)?;
+
+    Ok((merge(&base, own), Inheritance{base, own: own.clone(), overrides}))
+}
+
+impl Inheritance {
+    // Whether effective modpack entry comes from a parent
+    pub fn is_inherited(&self, entry: &Value) -> bool {
+        let identity = mod_identity(entry);
+        !sequence(&self.own, "Mods").iter().any(|m| identity.is_some() && mod_identity(m) == identity)
+            && sequence(&self.base, "Mods").iter().any(|m| identity.is_some() && mod_identity(m) == identity)
+    }
+
+    // Whether inherited entry differs from the one of parents, e.g. got side, names or pin since loading
+    pub fn is_changed(&self, entry: &Value) -> bool {
+        let identity = mod_identity(entry);
+        let base = sequence(&self.base, "Mods").into_iter()
+            .find(|m| identity.is_some() && mod_identity(m) == identity);
+
+        // Entry of parents is compared the way it would be written back
+        let base = base.and_then(|m| serde_yaml::from_value::<ModpackMod>(m).ok())
+            .and_then(|m| serde_yaml::to_value(m).ok());
+        base.as_ref() != Some(entry)
+    }
+
+    // Own `Minecraft` section for `current` effective one: values set in the modpack file and ones
+    // changed since loading are kept, everything else is left to parents
+    pub fn own_section(&self, current: &Mapping, own_mods: Vec<Value>) -> Mapping {
+        let mut result = Mapping::new();
+        for (name, value) in &self.own {
+            match name.as_str() {
+                Some("Extends") | Some("Include") | Some("Exclude") | Some("Replace") | Some("Mods") => {
+                    result.insert(name.clone(), value.clone());
+                },
+                _ => {
+                    if let Some(value) = current.get(name) {
+                        result.insert(name.clone(), value.clone());
+                    }
+                }
+            }
+        }
+
+        for (name, value) in current {
+            let inherited = matches!(name.as_str(), Some("Mods") | Some("Exclude") | Some("Replace"));
+            if !inherited && !result.contains_key(name) && self.base.get(name) != Some(value) {
+                result.insert(name.clone(), value.clone());
+            }
+        }
+
+        if !own_mods.is_empty() || self.own.contains_key(&key("Mods")) {
+            result.insert(key("Mods"), Value::Sequence(own_mods));
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yaml(contents: &str) -> Mapping {
+        serde_yaml::from_str(contents).unwrap()
+    }
+
+    fn mods(section: &Mapping) -> Vec<Value> {
+        sequence(section, "Mods")
+    }
+
+    // Directory with modpack files (name, `Minecraft` section)
+    fn packs(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mcget-inheritance-{}-{}", test, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, section) in files {
+            std::fs::write(dir.join(name), format!("Minecraft:\n{}", section)).unwrap();
+        }
+        dir
+    }
+
+    fn own(dir: &Path, name: &str) -> Mapping {
+        let contents: Value = serde_yaml::from_str(&std::fs::read_to_string(dir.join(name)).unwrap()).unwrap();
+        contents.get("Minecraft").unwrap().as_mapping().unwrap().clone()
+    }
+
+    #[test]
+    fn own_entry_replaces_inherited_one() {
+        let base = yaml("Mods: [{Id: 1, File: 10}, {Url: 'https://example.com/a.jar'}]");
+        let own = yaml("Mods: [{Id: 1, File: 11}, {Id: 2}]");
+
+        let merged = merge(&base, &own);
+        assert_eq!(mods(&merged), mods(&yaml(
+            "Mods: [{Id: 1, File: 11}, {Url: 'https://example.com/a.jar'}, {Id: 2}]"
+        )));
+    }
+
+    #[test]
+    fn exclude_drops_inherited_mod() {
+        let base = yaml("Exclude: [5]\nMods: [{Id: 1}, {Id: 2}]");
+        let own = yaml("Exclude: [2]\nMods: []");
+
+        let merged = merge(&base, &own);
+        assert_eq!(mods(&merged), mods(&yaml("Mods: [{Id: 1}]")));
+        assert_eq!(sequence(&merged, "Exclude"), sequence(&yaml("Exclude: [5, 2]"), "Exclude"));
+    }
+
+    #[test]
+    fn exclude_takes_only_ids() {
+        let location = PackLocation::File(PathBuf::from("pack.yaml"));
+        assert!(check_exclude(&yaml("Exclude: [1, 2]"), &location).is_ok());
+
+        let error = check_exclude(&yaml("Exclude: [1, 'https://example.com/a.jar']"), &location).unwrap_err();
+        assert!(error.to_string().contains("only CurseForge mod ids"));
+    }
+
+    #[test]
+    fn cycle_is_reported() {
+        let dir = packs("cycle", &[
+            ("a.yaml", "  Extends: b.yaml\n  Mods: []\n"),
+            ("b.yaml", "  Extends: a.yaml\n  Mods: []\n")
+        ]);
+
+        let a = dir.join("a.yaml");
+        let error = inherit(&a.to_string_lossy(), &own(&dir, "a.yaml")).err().unwrap().to_string();
+        let (a, b) = (a.canonicalize().unwrap(), dir.join("b.yaml").canonicalize().unwrap());
+        assert_eq!(error, format!("modpack inheritance cycle: {} -> {} -> {}", a.display(), b.display(), a.display()));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn diamond_include_applies_overrides_once() {
+        let dir = packs("diamond", &[
+            ("d.yaml", "  Overrides: d-overrides\n  Mods: [{Id: 1}]\n"),
+            ("b.yaml", "  Extends: d.yaml\n  Overrides: b-overrides\n  Mods: [{Id: 2}]\n"),
+            ("c.yaml", "  Extends: d.yaml\n  Overrides: c-overrides\n  Mods: [{Id: 3}]\n"),
+            ("a.yaml", "  Include: [b.yaml, c.yaml]\n  Mods: []\n")
+        ]);
+
+        let a = dir.join("a.yaml");
+        let (section, inheritance) = inherit(&a.to_string_lossy(), &own(&dir, "a.yaml")).unwrap();
+        assert_eq!(mods(&section), mods(&yaml("Mods: [{Id: 1}, {Id: 2}, {Id: 3}]")));
+
+        let overrides: Vec<String> = inheritance.overrides.iter()
+            .map(|o| o.overrides.as_ref().unwrap().file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(overrides, vec!["d-overrides", "b-overrides", "c-overrides"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn own_section_keeps_only_changed_inherited_entries() {
+        let base = yaml("Version: 1.20.1\nMods: [{Id: 1}, {Id: 2}]");
+        let own = yaml("Extends: base.yaml\nMods: [{Id: 3}]");
+        let inheritance = Inheritance{base: base.clone(), own: own.clone(), overrides: vec![]};
+
+        // Entries the way `ModpackCfg::store` serializes them, entry 2 got its side since loading
+        let entries: Vec<(bool, Value)> = ["Id: 1", "Id: 2\nSide: client", "Id: 3"].iter().map(|entry| {
+            let mod_: ModpackMod = serde_yaml::from_str(entry).unwrap();
+            let inherited = !entry.starts_with("Id: 3");
+            (inherited, serde_yaml::to_value(mod_).unwrap())
+        }).collect();
+        assert!(!inheritance.is_changed(&entries[0].1));
+        assert!(inheritance.is_changed(&entries[1].1));
+
+        let own_mods: Vec<Value> = entries.iter()
+            .filter(|(inherited, entry)| !inherited || inheritance.is_changed(entry))
+            .map(|(_, entry)| entry.clone()).collect();
+        let current = merge(&base, &own);
+        let section = inheritance.own_section(&current, own_mods);
+
+        let ids: Vec<Option<u64>> = mods(&section).iter().map(|m| m.get("Id").and_then(|i| i.as_u64())).collect();
+        assert_eq!(ids, vec![Some(2), Some(3)]);
+        assert_eq!(section.get(&key("Extends")), Some(&key("base.yaml")));
+        assert!(!section.contains_key(&key("Version")));
+    }
+}
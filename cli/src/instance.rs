@@ -0,0 +1,263 @@
+use {
+    std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf}
+    },
+    serde::{Serialize, Deserialize},
+
+    curseforge::prelude::*,
+    crate::{modpack::*, config::*, output::print_warning, curseforge_pack::extract_zip_dir}
+};
+
+// Directories of game directory that belong to a modpack unless it says otherwise
+pub const DEFAULT_MANAGED_DIRS: [&str; 6] = [
+    "mods", "config", "resourcepacks", "shaderpacks", "kubejs", "scripts"
+];
+
+const STATE_FILE: &str = ".mcget.yaml";
+
+// Installed modpack state kept in its instance directory
+#[derive(Serialize, Deserialize)]
+pub struct InstanceState {
+    #[serde(rename = "ManagedDirs")]
+    pub managed_dirs: Vec<String>,
+
+    // Hashes of installed override files, used to detect files modified by user
+    #[serde(rename = "Overrides", default)]
+    pub overrides: BTreeMap<String, String>
+}
+
+// Directory with everything of modpack except mods
+pub fn instance_dir(name: &str) -> PathBuf {
+    get_config_location().join("instances").join(name)
+}
+
+// Directory with downloaded mods of modpack
+pub fn pack_mods_dir(name: &str) -> PathBuf {
+    get_config_location().join("modpacks").join(name)
+}
+
+impl InstanceState {
+    // Packs installed before instances existed manage only mods
+    pub fn load(name: &str) -> InstanceState {
+        let path = instance_dir(name).join(STATE_FILE);
+        let state = std::fs::read_to_string(path).ok().and_then(
+            |contents| serde_yaml::from_str(&contents).ok()
+        );
+
+        state.unwrap_or_else(|| InstanceState{
+            managed_dirs: vec!["mods".to_string()],
+            overrides: Default::default()
+        })
+    }
+
+    pub fn store(&self, name: &str) -> RResult<()> {
+        let dir = instance_dir(name);
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join(STATE_FILE), serde_yaml::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    // Where managed directory of modpack is stored
+    pub fn source(name: &str, dir: &str) -> PathBuf {
+        if dir == "mods" {
+            pack_mods_dir(name)
+        } else {
+            instance_dir(name).join(dir)
+        }
+    }
+}
+
+fn file_hash(path: &Path) -> Option<String> {
+    std::fs::read(path).ok().map(|data| sha1_hex(&data))
+}
+
+// Overrides don't go through the downloader, so their jars are scanned before they are installed
+pub fn scan_overrides(dir: &Path, scan: ScanMode) -> RResult<()> {
+    for jar in check_path(dir, scan)? {
+        for finding in &jar.findings {
+            print_warning(format!("{} is {}: {}", jar.file, finding.severity, finding));
+        }
+    }
+
+    Ok(())
+}
+
+// Creates instance directories of modpack and installs its overrides,
+// files changed by user since previous installation are kept; returns kept files
+pub fn install_instance(pack: &ModpackCfg, scan: ScanMode) -> RResult<Vec<String>> {
+    let name = &pack.mc.name;
+    let mut state = InstanceState::load(name);
+    state.managed_dirs = pack.mc.managed_dirs();
+
+    for dir in &state.managed_dirs {
+        std::fs::create_dir_all(InstanceState::source(name, dir))?;
+    }
+
+    let mut kept = vec![];
+    for overrides in pack.overrides_dirs().iter().chain(&pack.client_overrides_dirs()) {
+        scan_overrides(overrides, scan)?;
+
+        let mut files = vec![];
+        list_files(overrides, Path::new(""), &mut files)?;
+
+        for file in files {
+            let relative = file.to_string_lossy().replace('\\', "/");
+            let dest = match file.strip_prefix("mods") {
+                Ok(rest) => pack_mods_dir(name).join(rest),
+                Err(_) => instance_dir(name).join(&file)
+            };
+
+            let contents = std::fs::read(overrides.join(&file))?;
+            let hash = sha1_hex(&contents);
+            if let Some(current) = file_hash(&dest) {
+                let modified = state.overrides.get(&relative) != Some(&current);
+                if current != hash && modified {
+                    kept.push(relative);
+                    continue;
+                }
+            }
+
+            std::fs::create_dir_all(dest.parent().unwrap())?;
+            std::fs::write(&dest, contents)?;
+            state.overrides.insert(relative, hash);
+        }
+    }
+
+    state.store(name)?;
+
+    Ok(kept)
+}
+
+const HISTORY_FILE: &str = "history.yaml";
+const HISTORY_LIMIT: usize = 20;
+
+// Previously active modpacks, most recent last
+#[derive(Serialize, Deserialize, Default)]
+pub struct SwitchHistory {
+    #[serde(rename = "Packs", default)]
+    pub packs: Vec<String>
+}
+
+impl SwitchHistory {
+    pub fn load() -> SwitchHistory {
+        let path = get_config_location().join(HISTORY_FILE);
+        std::fs::read_to_string(path).ok()
+            .and_then(|contents| serde_yaml::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn store(&self) -> RResult<()> {
+        let path = get_config_location().join(HISTORY_FILE);
+        std::fs::write(path, serde_yaml::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    pub fn push(&mut self, name: &str) {
+        if self.packs.last().map(|p| p == name).unwrap_or(false) {
+            return;
+        }
+
+        self.packs.push(name.to_string());
+        if self.packs.len() > HISTORY_LIMIT {
+            self.packs.remove(0);
+        }
+    }
+}
+
+// Name of modpack which game directory symlink points to
+pub fn linked_modpack(link: &Path) -> Option<String> {
+    let target = std::fs::read_link(link).ok()?;
+    let location = get_config_location();
+
+    for parent in ["modpacks", "instances"] {
+        if let Ok(rest) = target.strip_prefix(location.join(parent)) {
+            return rest.components().next()
+                .map(|c| c.as_os_str().to_string_lossy().to_string());
+        }
+    }
+
+    None
+}
+
+fn move_dir(src: &Path, dst: &Path) -> std::io::Result<()> {
+    if std::fs::rename(src, dst).is_ok() {
+        return Ok(());
+    }
+
+    // Different filesystems
+    copy_dir(src, dst)?;
+    std::fs::remove_dir_all(src)
+}
+
+// Moves real (not symlinked) game directories into new backup modpack, so they can be
+// switched back to later; returns name of the backup modpack if anything was moved
+pub fn backup_dirs(minecraft: &Path, dirs: &[String]) -> RResult<Option<String>> {
+    let real: Vec<&String> = dirs.iter()
+        .filter(|dir| {
+            let path = minecraft.join(dir);
+            path.is_dir() && !is_symlink(&path)
+        })
+        .collect();
+
+    let mut moved = vec![];
+    for dir in &real {
+        let path = minecraft.join(dir);
+        if std::fs::read_dir(&path)?.next().is_none() {
+            std::fs::remove_dir(&path)?;
+        } else {
+            moved.push(dir.to_string());
+        }
+    }
+
+    if moved.is_empty() {
+        return Ok(None);
+    }
+
+    let time = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?.as_secs();
+    let name = format!("backup-{}", time);
+
+    std::fs::create_dir_all(pack_mods_dir(&name))?;
+    std::fs::create_dir_all(instance_dir(&name))?;
+    for dir in &moved {
+        let dest = InstanceState::source(&name, dir);
+        if dir == "mods" {
+            std::fs::remove_dir(&dest)?;
+        }
+        move_dir(&minecraft.join(dir), &dest)?;
+    }
+
+    if !moved.iter().any(|d| d == "mods") {
+        moved.insert(0, "mods".to_string());
+    }
+
+    let state = InstanceState{managed_dirs: moved, overrides: Default::default()};
+    state.store(&name)?;
+
+    Ok(Some(name))
+}
+
+// Extracts freshly downloaded world archives into their `saves` directory and removes them,
+// returns paths of archives that failed with the reason
+pub fn extract_worlds(report: &DownloadReport) -> Vec<(String, String)> {
+    let mut failed = vec![];
+    for entry in report.entries.iter().filter(|e| e.kind == ProjectKind::World) {
+        if entry.status != DownloadStatus::Downloaded {
+            continue;
+        }
+
+        let path = PathBuf::from(entry.path.as_ref().unwrap());
+        let extracted = std::fs::File::open(&path).map_err(|e| e.into())
+            .and_then(|f| zip::ZipArchive::new(f).map_err(|e| e.into()))
+            .and_then(|mut archive| extract_zip_dir(&mut archive, "", path.parent().unwrap()));
+        if let Err(e) = extracted {
+            failed.push((entry.path.clone().unwrap(), e.to_string()));
+        }
+        std::fs::remove_file(&path).unwrap_or_default();
+    }
+
+    failed
+}
@@ -0,0 +1,111 @@
+use {
+    std::path::{Path, PathBuf},
+    serde::Serialize,
+    serde_json::{Value, Map},
+    symlink::{symlink_dir, remove_symlink_dir},
+
+    curseforge::prelude::*,
+    crate::{modpack::*, config::*, instance::*}
+};
+
+const DEFAULT_ICON: &str = "Furnace";
+
+// Version id the loader installer creates in `versions` directory
+pub fn loader_version_id(version: &str, loader: &ModLoader, loader_version: Option<&str>) -> String {
+    let loader_version = match loader_version {
+        Some(v) if !v.is_empty() => v,
+        _ => { return version.to_string(); }
+    };
+
+    match loader {
+        ModLoader::Forge => format!("{}-forge-{}", version, loader_version),
+        ModLoader::NeoForge => format!("neoforge-{}", loader_version),
+        ModLoader::Fabric => format!("fabric-loader-{}-{}", loader_version, version),
+        ModLoader::Quilt => format!("quilt-loader-{}-{}", loader_version, version),
+        _ => version.to_string()
+    }
+}
+
+// Installs instance of downloaded modpack to be used as game directory, `mods` links to the modpack
+pub fn create_game_dir(pack: &ModpackCfg, scan: ScanMode) -> RResult<PathBuf> {
+    let installed = pack_mods_dir(&pack.mc.name);
+    if !installed.exists() {
+        return Err(format!("{} is not downloaded yet", pack.mc.name).into());
+    }
+
+    install_instance(pack, scan)?;
+
+    let game_dir = instance_dir(&pack.mc.name);
+    let mods = game_dir.join("mods");
+    if is_symlink(&mods) {
+        remove_symlink_dir(&mods)?;
+    } else if mods.exists() {
+        std::fs::remove_dir_all(&mods)?;
+    }
+    symlink_dir(&installed, &mods)?;
+
+    Ok(game_dir)
+}
+
+#[derive(Serialize)]
+pub struct LauncherProfile {
+    pub key: String,
+    pub name: String,
+
+    #[serde(rename = "gameDir")]
+    pub game_dir: String,
+
+    #[serde(rename = "lastVersionId")]
+    pub last_version_id: String,
+
+    pub icon: String
+}
+
+impl LauncherProfile {
+    pub fn new(pack: &ModpackCfg, game_dir: &Path,
+               loader_version: Option<&str>, icon: Option<&String>) -> RResult<LauncherProfile> {
+        Ok(LauncherProfile{
+            key: format!("mcget-{}", pack.mc.name),
+            name: pack.mc.name.clone(),
+            game_dir: game_dir.to_string_lossy().to_string(),
+            last_version_id: loader_version_id(&pack.mc.exact_version()?, &pack.mc.loader, loader_version),
+            icon: icon.cloned().unwrap_or_else(|| DEFAULT_ICON.to_string())
+        })
+    }
+
+    // Adds or updates profile in launcher_profiles.json, other profiles and keys are left as they are
+    pub fn store(&self, minecraft: &Path) -> RResult<()> {
+        let path = minecraft.join("launcher_profiles.json");
+        let contents = std::fs::read_to_string(&path).unwrap_or_default();
+        let mut root: Value = if contents.trim().is_empty() {
+            Value::Object(Map::new())
+        } else {
+            serde_json::from_str(&contents).map_err(|e| format!("{}: {}", path.display(), e))?
+        };
+
+        let profiles = root.as_object_mut()
+            .ok_or("launcher_profiles.json is not an object")?
+            .entry("profiles").or_insert_with(|| Value::Object(Map::new()))
+            .as_object_mut()
+            .ok_or("profiles of launcher_profiles.json is not an object")?;
+
+        let profile = profiles.entry(self.key.clone()).or_insert_with(|| Value::Object(Map::new()))
+            .as_object_mut()
+            .ok_or(format!("profile {} is not an object", self.key))?;
+
+        profile.insert("name".to_string(), Value::String(self.name.clone()));
+        profile.insert("type".to_string(), Value::String("custom".to_string()));
+        profile.insert("gameDir".to_string(), Value::String(self.game_dir.clone()));
+        profile.insert("lastVersionId".to_string(), Value::String(self.last_version_id.clone()));
+        profile.insert("icon".to_string(), Value::String(self.icon.clone()));
+
+        // The launcher writes two-space indented JSON too, so untouched parts stay the same
+        let mut result = serde_json::to_string_pretty(&root)?;
+        if contents.ends_with('\n') {
+            result.push('\n');
+        }
+        std::fs::write(&path, result)?;
+
+        Ok(())
+    }
+}
@@ -1,5 +1,19 @@
 pub mod cli;
 pub mod config;
 pub mod modpack;
+pub mod output;
+pub mod lock;
+pub mod curseforge_pack;
+pub mod mrpack;
+pub mod packwiz;
+pub mod prism;
+pub mod launcher;
+pub mod instance;
+pub mod server_pack;
+pub mod loader_check;
+pub mod migrate;
+pub mod check;
+pub mod import_dir;
+pub mod inheritance;
 
 pub mod prelude;
@@ -0,0 +1,79 @@
+use {
+    std::path::Path,
+    serde::Serialize,
+
+    curseforge::prelude::*,
+    crate::modpack::*
+};
+
+// Mod which needs mod loader version the pack doesn't guarantee
+#[derive(Serialize)]
+pub struct LoaderIssue {
+    pub file: String,
+    pub requires: String,
+    pub allowed: String
+}
+
+// Loader version ranges declared by jar for mod `loader`
+pub fn jar_loader_requirements(path: &Path, loader: &ModLoader) -> Vec<VersionRange> {
+    JarMeta::read(path).as_ref()
+        .and_then(|jar| jar.for_loader(loader))
+        .map(|meta| meta.loader_requirements(loader))
+        .unwrap_or_default()
+}
+
+// Whether every loader version of `allowed` (pack LoaderVersion) fits `required`,
+// ranges are compared by their lowest and highest versions
+pub fn loader_version_allows(allowed: &VersionRange, required: &VersionRange) -> bool {
+    match allowed.as_exact() {
+        Some(version) => required.contains(version),
+        None => !allowed.allows_below(required) && !allowed.allows_above(required)
+    }
+}
+
+// Downloaded mods requiring loader version the pack's `LoaderVersion` doesn't guarantee
+pub fn check_loader_version(pack: &MinecraftModpack, report: &DownloadReport) -> RResult<Vec<LoaderIssue>> {
+    let allowed = match &pack.loader_version {
+        Some(version) => version,
+        None => { return Ok(vec![]); }
+    };
+    let range = VersionRange::parse(allowed).ok_or(format!("invalid LoaderVersion {}", allowed))?;
+
+    let mut issues = vec![];
+    for entry in report.entries.iter().filter(|e| !e.is_failed() && e.kind.is_mod()) {
+        let path = Path::new(entry.path.as_ref().unwrap());
+        for required in jar_loader_requirements(path, &pack.loader) {
+            if !loader_version_allows(&range, &required) {
+                issues.push(LoaderIssue{
+                    file: path.file_name().unwrap().to_string_lossy().to_string(),
+                    requires: required.to_string(),
+                    allowed: allowed.clone()
+                });
+            }
+        }
+    }
+
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allows(allowed: &str, required: &str) -> bool {
+        loader_version_allows(&VersionRange::parse(allowed).unwrap(), &VersionRange::parse(required).unwrap())
+    }
+
+    #[test]
+    fn pack_range_has_to_fit_requirement() {
+        assert!(allows("[47.2,48)", "[47,)"));
+        assert!(allows("[47.2,48)", "[47,48)"));
+        assert!(allows("47.2.0", "[47,47.3)"));
+
+        // Lower and upper ends of the pack range
+        assert!(!allows("[46,48)", "[47,)"));
+        assert!(!allows("[47.2,48)", "[47,47.1)"));
+        assert!(!allows("[47,)", "[47,48)"));
+        assert!(!allows("47.2.0", "[47,47.1)"));
+    }
+}
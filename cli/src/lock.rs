@@ -0,0 +1,120 @@
+use {
+    std::path::Path,
+    serde::{Serialize, Deserialize},
+    colored::*,
+
+    curseforge::prelude::*
+};
+
+// Exact file installed for a modpack entry
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct LockedFile {
+    #[serde(rename = "Id", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<usize>,
+
+    #[serde(rename = "File", default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<usize>,
+
+    // Empty for local files
+    #[serde(rename = "Url")]
+    pub url: String,
+
+    // Local file relative to the modpack file
+    #[serde(rename = "Source", default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+
+    // Path inside of the game directory, e.g. mods/jei.jar
+    #[serde(rename = "Path")]
+    pub path: String,
+
+    #[serde(rename = "Size")]
+    pub size: usize,
+
+    #[serde(rename = "Sha1")]
+    pub sha1: String,
+
+    #[serde(rename = "Sha512")]
+    pub sha512: String
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct ModpackLock {
+    #[serde(skip)]
+    pub file: String,
+
+    #[serde(rename = "Files")]
+    pub files: Vec<LockedFile>
+}
+
+impl ModpackLock {
+    pub fn new(file: String) -> ModpackLock {
+        ModpackLock{file, files: Default::default()}
+    }
+
+    // Lock built from successfully installed entries of download report,
+    // failed entries keep what `previous` lock says about them
+    pub fn from_report(file: String, report: &DownloadReport, previous: Option<&ModpackLock>) -> ModpackLock {
+        let mut lock = Self::new(file);
+        let root = Path::new(&lock.file).parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+        for entry in report.failed() {
+            let path = match entry.path.as_ref().and_then(|p| Path::new(p).file_name()) {
+                Some(name) => format!("{}/{}", entry.kind.dir(), name.to_string_lossy()),
+                None => { continue; }
+            };
+            if let Some(locked) = previous.and_then(|p| p.files.iter().find(|f| f.path == path)) {
+                lock.files.push(locked.clone());
+            }
+        }
+
+        for entry in report.entries.iter().filter(|e| !e.is_failed()) {
+            let filename = Path::new(entry.path.as_ref().unwrap()).file_name().unwrap();
+            let source = entry.source.as_ref().map(|s| {
+                let s = Path::new(s);
+                s.strip_prefix(&root).unwrap_or(s).to_string_lossy().replace('\\', "/")
+            });
+            lock.files.push(LockedFile{
+                id: entry.id,
+                file: entry.file,
+                url: entry.url.clone().unwrap_or_default(),
+                source,
+                path: format!("{}/{}", entry.kind.dir(), filename.to_string_lossy()),
+                size: entry.bytes,
+                sha1: entry.sha1.clone().unwrap_or_default(),
+                sha512: entry.sha512.clone().unwrap_or_default()
+            });
+        }
+
+        lock.files.sort_by(|a, b| a.path.cmp(&b.path));
+        lock
+    }
+
+    // Lock file of modpack, None if it doesn't exist yet
+    pub fn load(filename: &str) -> Option<ModpackLock> {
+        let contents = std::fs::read_to_string(filename).ok()?;
+
+        match serde_yaml::from_str::<ModpackLock>(&contents) {
+            Ok(mut r) => {
+                r.file = filename.to_string();
+                Some(r)
+            },
+            Err(e) => {
+                println!("Failed to parse lock file {}: {}", filename, e.to_string().red());
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Hashes of file locked at `path` inside of the game directory, e.g. mods/jei.jar
+    pub fn expected(&self, path: &str) -> ExpectedFile {
+        match self.files.iter().find(|f| f.path == path) {
+            Some(f) => ExpectedFile{size: Some(f.size), sha1: Some(f.sha1.clone()).filter(|h| !h.is_empty()),
+                                    sha512: Some(f.sha512.clone()).filter(|h| !h.is_empty()), fingerprint: None},
+            None => Default::default()
+        }
+    }
+
+    pub fn store(&self) {
+        let content = serde_yaml::to_string(self).unwrap();
+        std::fs::write(&self.file, content).unwrap_or_default();
+    }
+}
@@ -0,0 +1,157 @@
+use {
+    std::collections::{HashMap, HashSet, VecDeque},
+    serde::Serialize,
+
+    curseforge::prelude::*,
+    crate::modpack::*
+};
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Readiness {
+    Ready,
+
+    // Only beta or alpha files are made for the target version
+    OnlyBeta,
+    Missing,
+
+    // Entries downloaded by url can't be checked
+    Unknown,
+
+    // CurseForge request for files of the mod failed, see `MigrateMod::error`
+    LookupFailed
+}
+
+#[derive(Serialize)]
+pub struct MigrateMod {
+    pub id: Option<usize>,
+    pub name: String,
+    pub status: Readiness,
+    pub required: bool,
+
+    // Mod which pulled this one as a dependency, None for modpack entries
+    pub dependency_of: Option<usize>,
+    pub file: Option<ModFile>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>
+}
+
+#[derive(Serialize)]
+pub struct MigrateReport {
+    pub from: String,
+    pub to: String,
+    pub loader: ModLoader,
+    pub mods: Vec<MigrateMod>
+}
+
+impl MigrateReport {
+    pub fn count(&self, status: Readiness) -> usize {
+        self.mods.iter().filter(|m| m.status == status).count()
+    }
+
+    // Required mods without a file for the target version
+    pub fn blocking(&self) -> Vec<&MigrateMod> {
+        self.mods.iter().filter(|m| m.required && m.status == Readiness::Missing).collect()
+    }
+
+    pub fn find(&self, id: usize) -> Option<&MigrateMod> {
+        self.mods.iter().find(|m| m.id == Some(id))
+    }
+}
+
+// Checks every CurseForge entry of modpack and its required dependencies for a file
+// matching `target`, `Exclude` and `Replace` rules are applied to dependencies
+pub async fn migrate_report(pack: &ModpackCfg, cf: &CurseForge,
+                            target: &GameVersion) -> RResult<MigrateReport> {
+    let mut mods = vec![];
+    let mut queue = VecDeque::new();
+    let mut seen = HashSet::new();
+    let mut index = HashMap::new();
+    let mut upgrades = vec![];
+    let rules = pack.mc.resolve_rules();
+
+    for mod_ in &pack.mc.mods {
+        match mod_.id {
+            Some(id) => {
+                seen.insert(id);
+                queue.push_back((id, mod_.kind, !mod_.optional, None));
+            },
+            None => mods.push(MigrateMod{
+                id: None, name: mod_.display_name(), status: Readiness::Unknown,
+                required: !mod_.optional, dependency_of: None, file: None, error: None
+            })
+        }
+    }
+
+    while let Some((id, kind, required, dependency_of)) = queue.pop_front() {
+        // Failed lookup is reported for this mod only, the rest are still checked
+        let (files, error) = match cf.files(id, target.clone().for_kind(kind)).await {
+            Ok(files) => (files, None),
+            Err(e) => (vec![], Some(e.to_string()))
+        };
+        let stable = files.iter().filter(|f| !f.is_beta()).cloned().collect::<Vec<ModFile>>();
+
+        let (status, file) = match (stable.latest(), files.latest()) {
+            _ if error.is_some() => (Readiness::LookupFailed, None),
+            (Ok(file), _) => (Readiness::Ready, Some(file.clone())),
+            (Err(_), Ok(file)) => (Readiness::OnlyBeta, Some(file.clone())),
+            _ => (Readiness::Missing, None)
+        };
+
+        let name = match pack.mc.mods.iter().find(|m| m.id == Some(id)) {
+            Some(mod_) => mod_.display_name(),
+            None => cf.mod_info(id).await.map(|m| m.name).unwrap_or_else(|_| id.to_string())
+        };
+
+        for dep in file.iter().flat_map(|f| &f.dependencies).filter(|d| d.type_ == 3) {
+            match rules.dependency(dep.addon_id) {
+                Some(dep_id) if seen.insert(dep_id) => queue.push_back((dep_id, ProjectKind::Mod, required, Some(id))),
+                Some(dep_id) if required => upgrades.push(dep_id),
+                _ => {}
+            }
+        }
+
+        index.insert(id, mods.len());
+        mods.push(MigrateMod{id: Some(id), name, status, required, dependency_of, file, error});
+    }
+
+    // Dependencies first reached from optional mods are required if a required mod needs them too
+    while let Some(id) = upgrades.pop() {
+        let mod_ = match index.get(&id) {
+            Some(idx) if !mods[*idx].required => &mut mods[*idx],
+            _ => { continue; }
+        };
+
+        mod_.required = true;
+        upgrades.extend(mod_.file.iter().flat_map(|f| &f.dependencies).filter(|d| d.type_ == 3)
+            .filter_map(|d| rules.dependency(d.addon_id)));
+    }
+
+    Ok(MigrateReport{from: pack.mc.version.clone(), to: target.version.clone(),
+                     loader: target.mod_loader.clone().unwrap_or_default(), mods})
+}
+
+// Copy of modpack for the target version: pins are moved to the found files
+// and entries without files are left out
+pub fn migrated_pack(pack: &ModpackCfg, report: &MigrateReport, file: &str) -> RResult<ModpackCfg> {
+    let mut migrated: ModpackCfg = serde_yaml::from_str(&serde_yaml::to_string(pack)?)?;
+    migrated.file = file.to_string();
+    migrated.mc.version = report.to.clone();
+    migrated.mc.loader = report.loader.clone();
+    migrated.mc.loader_version = None;
+
+    migrated.mc.mods.retain(|mod_| match mod_.id.and_then(|id| report.find(id)) {
+        Some(m) => m.status != Readiness::Missing,
+        None => true
+    });
+
+    for mod_ in migrated.mc.mods.iter_mut() {
+        let found = mod_.id.and_then(|id| report.find(id)).and_then(|m| m.file.as_ref());
+        if let (Some(_), Some(file)) = (mod_.file, found) {
+            mod_.file = Some(file.id);
+        }
+    }
+
+    Ok(migrated)
+}
@@ -1,14 +1,51 @@
 use {
-    serde::{Serialize, Deserialize}
+    std::path::Path,
+    serde::{Serialize, Deserialize},
+    curseforge::prelude::{Mod, ModLoader, ProjectKind, DownloadReport, VersionRange, url_filename,
+                           GameVersion, McVersion, VersionPolicy, Side, ResolveRules, RResult},
+    crate::{instance::DEFAULT_MANAGED_DIRS, inheritance::Inheritance}
 };
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Default)]
 pub struct ModpackMod {
     #[serde(rename = "Id")]
     pub id: Option<usize>,
 
     #[serde(rename = "Url")]
-    pub url: Option<String>
+    pub url: Option<String>,
+
+    // Local file relative to the modpack file, e.g. private/tools.jar
+    #[serde(rename = "Path", default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+
+    #[serde(rename = "File", default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<usize>,
+
+    #[serde(rename = "Name", default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    #[serde(rename = "Slug", default, skip_serializing_if = "Option::is_none")]
+    pub slug: Option<String>,
+
+    // Installation doesn't fail if optional mod can't be downloaded
+    #[serde(rename = "Optional", default, skip_serializing_if = "is_false")]
+    pub optional: bool,
+
+    // Resource packs, shader packs, etc. are installed next to mods
+    #[serde(rename = "Kind", default, skip_serializing_if = "ProjectKind::is_mod")]
+    pub kind: ProjectKind,
+
+    // Client or server only entries, both if omitted
+    #[serde(rename = "Side", default, skip_serializing_if = "Option::is_none")]
+    pub side: Option<Side>,
+
+    // Entry comes from extended or included modpack and isn't stored in this one
+    #[serde(skip)]
+    pub inherited: bool
+}
+
+fn is_false(v: &bool) -> bool {
+    !*v
 }
 
 #[derive(Serialize, Deserialize)]
@@ -17,19 +54,170 @@ pub struct MinecraftModpack {
     pub name: String,
 
     #[serde(rename = "ModLoader")]
-    pub loader: String,
+    pub loader: ModLoader,
 
+    // Exact minecraft version, range or wildcard, e.g. 1.20.1, >=1.20 <1.20.2 or 1.20.x
     #[serde(rename = "Version")]
     pub version: String,
 
+    // `same-minor` also accepts files made for other releases of the same minor version
+    #[serde(rename = "VersionPolicy", default, skip_serializing_if = "VersionPolicy::is_exact")]
+    pub version_policy: VersionPolicy,
+
+    // Exact mod loader version or range, e.g. 47.2.0 or [47.2,48)
+    #[serde(rename = "LoaderVersion", default, skip_serializing_if = "Option::is_none")]
+    pub loader_version: Option<String>,
+
+    // Directory with configs, scripts, etc., relative to the modpack file
+    #[serde(rename = "Overrides", default, skip_serializing_if = "Option::is_none")]
+    pub overrides: Option<String>,
+
+    // Overrides installed only on client or on server
+    #[serde(rename = "ClientOverrides", default, skip_serializing_if = "Option::is_none")]
+    pub client_overrides: Option<String>,
+
+    #[serde(rename = "ServerOverrides", default, skip_serializing_if = "Option::is_none")]
+    pub server_overrides: Option<String>,
+
+    // Game directories switched together with the modpack
+    #[serde(rename = "ManagedDirs", default, skip_serializing_if = "Option::is_none")]
+    pub managed_dirs: Option<Vec<String>>,
+
+    // CurseForge mods never added as dependencies, even if some mod requires them
+    #[serde(rename = "Exclude", default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<usize>,
+
+    // Dependencies resolved as another mod, e.g. a library fork
+    #[serde(rename = "Replace", default, skip_serializing_if = "Vec::is_empty")]
+    pub replace: Vec<ModReplacement>,
+
     #[serde(rename = "Mods")]
-    pub mods: Vec<ModpackMod>
+    pub mods: Vec<ModpackMod>,
+
+    // Release picked for range `Version`, see `CliApp::load_resolved`
+    #[serde(skip)]
+    pub resolved_version: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ModReplacement {
+    #[serde(rename = "From")]
+    pub from: usize,
+
+    #[serde(rename = "With")]
+    pub with: usize
 }
 
 impl MinecraftModpack {
+    pub fn resolve_rules(&self) -> ResolveRules {
+        ResolveRules{
+            exclude: self.exclude.iter().cloned().collect(),
+            replace: self.replace.iter().map(|r| (r.from, r.with)).collect()
+        }
+    }
+
     pub fn has_modid(&self, id: usize) -> bool {
         self.mods.iter().any(move |v| v.id.unwrap_or(0) == id)
     }
+
+    // CurseForge mods as (mod id, pinned file id) pairs
+    pub fn pinned_ids(&self) -> Vec<(usize, Option<usize>)> {
+        self.pinned_ids_for(Side::Both)
+    }
+
+    // Same as `pinned_ids`, but only mods installed on `side`
+    pub fn pinned_ids_for(&self, side: Side) -> Vec<(usize, Option<usize>)> {
+        self.mods.iter().filter(
+            |v| v.id.is_some() && v.installed_on(side)
+        ).map(
+            |v| (v.id.unwrap(), v.file)
+        ).collect()
+    }
+
+    // Fills in missing sides of mods from downloaded jars, returns true if anything changed
+    pub fn fill_sides(&mut self, report: &DownloadReport) -> bool {
+        let mut changed = false;
+        for entry in report.entries.iter().filter(|e| !e.is_failed() && e.kind.is_mod()) {
+            let side = match Side::from_jar(Path::new(entry.path.as_ref().unwrap())) {
+                Some(side) if side != Side::Both => side,
+                _ => { continue; }
+            };
+
+            let mod_ = self.mods.iter_mut().find(
+                |m| (m.id.is_some() && m.id == entry.id) || (m.url.is_some() && m.url == entry.url)
+            );
+            if let Some(mod_) = mod_.filter(|m| m.side.is_none()) {
+                mod_.side = Some(side);
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    // Managed directories always include directories of pack entries
+    pub fn managed_dirs(&self) -> Vec<String> {
+        let mut dirs = match &self.managed_dirs {
+            Some(dirs) => dirs.clone(),
+            None => DEFAULT_MANAGED_DIRS.iter().map(|d| d.to_string()).collect()
+        };
+
+        // Data packs aren't installed on client, see `download_fn`
+        for kind in self.mods.iter().map(|m| m.kind).filter(|k| *k != ProjectKind::DataPack) {
+            if !dirs.iter().any(|d| d == kind.dir()) {
+                dirs.push(kind.dir().to_string());
+            }
+        }
+
+        if !dirs.iter().any(|d| d == "mods") {
+            dirs.insert(0, "mods".to_string());
+        }
+        dirs
+    }
+
+    pub fn game_version(&self) -> GameVersion {
+        GameVersion::new(self.version.clone())
+            .with_loader(self.loader.clone())
+            .with_policy(self.version_policy)
+    }
+
+    // Version for exports and launchers, which need an exact one
+    pub fn exact_version(&self) -> RResult<String> {
+        if let Some(version) = &self.resolved_version {
+            return Ok(version.clone());
+        }
+
+        match McVersion::parse(&self.version) {
+            Some(version) => Ok(version.to_string()),
+            None => Err(format!("minecraft version {} isn't exact", self.version).into())
+        }
+    }
+
+    // LoaderVersion if it isn't a range
+    pub fn exact_loader_version(&self) -> Option<String> {
+        let range = VersionRange::parse(self.loader_version.as_ref()?)?;
+        range.as_exact().map(|v| v.to_string())
+    }
+
+    // Entry installed from CurseForge mod `id` or from `url`
+    pub fn find_entry(&self, id: Option<usize>, url: Option<&String>) -> Option<&ModpackMod> {
+        self.mods.iter().find(
+            |m| (m.id.is_some() && m.id == id) || (m.url.is_some() && m.url.as_ref() == url)
+        )
+    }
+
+    // Entry with local `Path`, relative to the modpack file
+    pub fn find_path(&self, path: &str) -> Option<&ModpackMod> {
+        self.mods.iter().find(|m| m.path.as_ref().map(|p| Path::new(p) == Path::new(path)).unwrap_or(false))
+    }
+
+    pub fn kind_of(&self, id: usize) -> ProjectKind {
+        self.mods.iter().find(|v| v.id == Some(id)).map(|v| v.kind).unwrap_or_default()
+    }
+
+    pub fn is_optional(&self, id: usize) -> bool {
+        self.mods.iter().any(move |v| v.id == Some(id) && v.optional)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -37,16 +225,75 @@ pub struct ModpackCfg {
     #[serde(skip)]
     pub file: String,
 
+    // What the modpack takes from `Extends` and `Include`, None if it has neither
+    #[serde(skip)]
+    pub inheritance: Option<Inheritance>,
+
     #[serde(rename = "Minecraft")]
     pub mc: MinecraftModpack
 }
 
 impl ModpackMod {
     pub fn with_id(id: usize) -> ModpackMod {
-        ModpackMod{id: Some(id), url: None}
+        ModpackMod{id: Some(id), ..Default::default()}
     }
 
     pub fn with_url(url: String) -> ModpackMod {
-        ModpackMod{id: None, url: Some(url)}
+        ModpackMod{url: Some(url), ..Default::default()}
+    }
+
+    pub fn with_path(path: String) -> ModpackMod {
+        ModpackMod{path: Some(path), ..Default::default()}
+    }
+
+    pub fn with_mod(mod_: &Mod) -> ModpackMod {
+        let mut result = Self::with_id(mod_.id);
+        result.kind = mod_.kind();
+        result.set_names(mod_);
+        result
+    }
+
+    pub fn set_names(&mut self, mod_: &Mod) {
+        self.name = Some(mod_.name.clone());
+        self.slug = Some(mod_.slug.clone());
+    }
+
+    // Human readable name: cached name, slug or whatever identifies the source
+    pub fn display_name(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        if let Some(slug) = &self.slug {
+            return slug.clone();
+        }
+
+        match (&self.id, &self.url, &self.path) {
+            (Some(id), _, _) => id.to_string(),
+            (None, Some(url), _) => url_filename(url),
+            (None, None, Some(path)) => Path::new(path).file_name()
+                .map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| path.clone()),
+            (None, None, None) => "<unknown>".to_string()
+        }
+    }
+
+    pub fn side(&self) -> Side {
+        self.side.unwrap_or_default()
+    }
+
+    // Whether entry is installed in client, server or any installation
+    pub fn installed_on(&self, side: Side) -> bool {
+        match side {
+            Side::Client => self.side().on_client(),
+            Side::Server => self.side().on_server(),
+            Side::Both => true
+        }
+    }
+
+    pub fn source(&self) -> &'static str {
+        match (&self.id, &self.url) {
+            (Some(_), _) => "curseforge",
+            (None, Some(_)) => "url",
+            (None, None) => "path"
+        }
     }
 }
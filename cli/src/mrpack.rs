@@ -0,0 +1,302 @@
+use {
+    std::{
+        collections::{BTreeMap, HashMap},
+        fs::File,
+        io::{Read, Write},
+        path::{Path, PathBuf}
+    },
+    serde::{Serialize, Deserialize},
+    zip::{ZipArchive, ZipWriter, write::FileOptions},
+    colored::*,
+
+    curseforge::prelude::*,
+    crate::{modpack::*, lock::*, output::*, curseforge_pack::*, instance::{InstanceState, scan_overrides}}
+};
+
+// Modrinth modpack `modrinth.index.json`
+#[derive(Serialize, Deserialize)]
+pub struct MrpackIndex {
+    #[serde(rename = "formatVersion")]
+    pub format_version: usize,
+
+    pub game: String,
+
+    #[serde(rename = "versionId")]
+    pub version_id: String,
+
+    pub name: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+
+    pub files: Vec<MrpackFile>,
+
+    // minecraft, forge, neoforge, fabric-loader or quilt-loader => version
+    pub dependencies: BTreeMap<String, String>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MrpackFile {
+    pub path: String,
+    pub hashes: MrpackHashes,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<MrpackEnv>,
+
+    pub downloads: Vec<String>,
+
+    #[serde(rename = "fileSize")]
+    pub file_size: usize
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MrpackHashes {
+    pub sha1: String,
+    pub sha512: String
+}
+
+// Each side is one of required, optional or unsupported
+#[derive(Serialize, Deserialize)]
+pub struct MrpackEnv {
+    pub client: String,
+    pub server: String
+}
+
+pub struct MrpackExport {
+    pub output: PathBuf,
+    pub loader_version: String,
+    pub version: String,
+
+    // Put files from hosts Modrinth doesn't accept into overrides
+    pub bundle_foreign: bool
+}
+
+// Hosts Modrinth accepts in `downloads` of modpack files
+const MRPACK_HOSTS: [&str; 4] = ["cdn.modrinth.com", "github.com", "raw.githubusercontent.com", "gitlab.com"];
+
+pub fn is_mrpack_download(url: &str) -> bool {
+    let host = url.strip_prefix("https://").and_then(|u| u.split('/').next()).unwrap_or_default();
+    MRPACK_HOSTS.contains(&host)
+}
+
+// Modrinth dependency name of mod loader
+pub fn mrpack_loader(loader: &ModLoader) -> Option<&'static str> {
+    match loader {
+        ModLoader::Forge => Some("forge"),
+        ModLoader::NeoForge => Some("neoforge"),
+        ModLoader::Fabric => Some("fabric-loader"),
+        ModLoader::Quilt => Some("quilt-loader"),
+        _ => None
+    }
+}
+
+impl MrpackIndex {
+    // Mod loader as (loader, loader version)
+    pub fn loader(&self) -> Option<(ModLoader, String)> {
+        self.dependencies.iter().find(|(k, _)| *k != "minecraft").map(
+            |(k, v)| (ModLoader::parse(k), v.clone())
+        )
+    }
+}
+
+fn overrides_name(output: &std::path::Path, folder: &str) -> String {
+    format!("{}-{}", output.file_stem().unwrap().to_string_lossy(), folder)
+}
+
+// Reads .mrpack into modpack stored at `output` with lock file holding hashes,
+// override folders are extracted next to it and their jars are scanned
+pub async fn import_mrpack(mrpack: &str, output: Option<&String>, scan: ScanMode) -> RResult<ModpackCfg> {
+    let mut archive = ZipArchive::new(File::open(mrpack)?)?;
+
+    let index: MrpackIndex = {
+        let mut entry = archive.by_name("modrinth.index.json")
+            .map_err(|_| format!("{} has no modrinth.index.json", mrpack))?;
+        let mut contents = String::new();
+        entry.read_to_string(&mut contents)?;
+
+        serde_json::from_str(&contents)?
+    };
+
+    let output = match output {
+        Some(o) => PathBuf::from(o),
+        None => PathBuf::from(sanitize_pack_name(&index.name) + ".yaml")
+    };
+
+    let version = index.dependencies.get("minecraft").cloned()
+        .ok_or("modrinth.index.json has no minecraft dependency")?;
+    let loader = index.loader().map(|l| l.0).unwrap_or_default();
+    let mut pack = ModpackCfg::new(sanitize_pack_name(&index.name), version, loader,
+                                   output.to_string_lossy().to_string());
+    pack.mc.loader_version = index.loader().map(|l| l.1);
+    let mut lock = ModpackLock::new(pack.lock_file());
+
+    // Files without `env` get sides of their Modrinth projects
+    let hashes: Vec<String> = index.files.iter().filter(|f| f.env.is_none())
+        .map(|f| f.hashes.sha512.clone()).collect();
+    let sides = Modrinth::new().sides(&hashes).await.unwrap_or_else(|e| {
+        print_warning(format!("Modrinth side lookup failed: {}", e));
+        HashMap::new()
+    });
+
+    for file in &index.files {
+        let kind = match file.path.split_once('/') {
+            Some((dir, name)) if !name.contains('/') => ProjectKind::from_dir(dir),
+            _ => None
+        };
+        let (url, kind) = match (file.downloads.first(), kind) {
+            (Some(url), Some(kind)) if kind != ProjectKind::World => (url.clone(), kind),
+            _ => {
+                if !json_output() {
+                    println!("{} {} is not imported", "Skipped".yellow(), file.path.bold());
+                }
+                continue;
+            }
+        };
+
+        let mut mod_ = ModpackMod::with_url(url.clone());
+        mod_.kind = kind;
+        mod_.optional = file.env.as_ref().map(|e| e.client == "optional").unwrap_or(false);
+        mod_.side = file.env.as_ref()
+            .map(|e| Side::from_modrinth(&e.client, &e.server))
+            .or_else(|| sides.get(&file.hashes.sha512).copied())
+            .filter(|s| *s != Side::Both);
+        pack.mc.mods.push(mod_);
+
+        lock.files.push(LockedFile{
+            id: None, file: None, url, source: None,
+            path: file.path.clone(),
+            size: file.file_size,
+            sha1: file.hashes.sha1.clone(),
+            sha512: file.hashes.sha512.clone()
+        });
+    }
+
+    let parent = output.parent().unwrap_or_else(|| std::path::Path::new("")).to_path_buf();
+    for folder in ["overrides", "client-overrides", "server-overrides"] {
+        let name = overrides_name(&output, folder);
+        if extract_zip_dir(&mut archive, folder, &parent.join(&name))? == 0 {
+            continue;
+        }
+        scan_overrides(&parent.join(&name), scan)?;
+
+        match folder {
+            "overrides" => { pack.mc.overrides = Some(name); },
+            "client-overrides" => { pack.mc.client_overrides = Some(name); },
+            _ => { pack.mc.server_overrides = Some(name); }
+        }
+    }
+
+    pack.store();
+    lock.store();
+
+    Ok(pack)
+}
+
+// Writes installed modpack as .mrpack, files are taken from the lock file
+pub fn export_mrpack(pack: &ModpackCfg, opts: &MrpackExport) -> RResult<()> {
+    let lock = ModpackLock::load(&pack.lock_file())
+        .ok_or(format!("{} has no lock file, download modpack first", pack.file))?;
+    let loader = mrpack_loader(&pack.mc.loader)
+        .ok_or(format!("mod loader {} is not supported by Modrinth", pack.mc.loader))?;
+
+    let mut dependencies = BTreeMap::new();
+    dependencies.insert("minecraft".to_string(), pack.mc.exact_version()?);
+    dependencies.insert(loader.to_string(), opts.loader_version.clone());
+
+    let mut files = vec![];
+    let mut local = vec![];
+    let mut foreign = vec![];
+    for locked in &lock.files {
+        let folder = |side: Option<Side>| match side {
+            Some(Side::Client) => "client-overrides",
+            Some(Side::Server) => "server-overrides",
+            _ => "overrides"
+        };
+
+        // Local files have no download url and are shipped as overrides
+        if let Some(source) = &locked.source {
+            let side = pack.mc.find_path(source).map(|m| m.side());
+            local.push((format!("{}/{}", folder(side), locked.path), pack.pack_path(source)));
+            continue;
+        }
+
+        let entry = pack.mc.find_entry(locked.id, Some(&locked.url));
+        let optional = entry.map(|m| m.optional).unwrap_or(false);
+        let side = entry.map(|m| m.side()).unwrap_or_default();
+
+        // Installed copy is shipped instead of file from host Modrinth rejects
+        if !is_mrpack_download(&locked.url) {
+            match (opts.bundle_foreign, locked.path.split_once('/')) {
+                (true, Some((dir, file))) => {
+                    local.push((format!("{}/{}", folder(Some(side)), locked.path),
+                                InstanceState::source(&pack.mc.name, dir).join(file)));
+                },
+                _ => foreign.push(locked.path.clone())
+            }
+            continue;
+        }
+
+        files.push(MrpackFile{
+            path: locked.path.clone(),
+            hashes: MrpackHashes{sha1: locked.sha1.clone(), sha512: locked.sha512.clone()},
+            env: if optional || side != Side::Both {
+                let env = |installed: bool| match (installed, optional) {
+                    (false, _) => "unsupported",
+                    (true, true) => "optional",
+                    (true, false) => "required"
+                }.to_string();
+                Some(MrpackEnv{client: env(side.on_client()), server: env(side.on_server())})
+            } else {
+                None
+            },
+            downloads: vec![locked.url.clone()],
+            file_size: locked.size
+        });
+    }
+
+    if !foreign.is_empty() {
+        return Err(format!("Modrinth doesn't accept downloads of {} from their hosts, \
+                            pass --bundle-foreign to put them into overrides", foreign.join(", ")).into());
+    }
+
+    let index = MrpackIndex{
+        format_version: 1,
+        game: "minecraft".to_string(),
+        version_id: opts.version.clone(),
+        name: pack.mc.name.clone(),
+        summary: None,
+        files, dependencies
+    };
+
+    // Partially written archive is removed
+    let result = write_mrpack(pack, &index, &local, &opts.output);
+    if result.is_err() {
+        std::fs::remove_file(&opts.output).unwrap_or_default();
+    }
+
+    result
+}
+
+fn write_mrpack(pack: &ModpackCfg, index: &MrpackIndex, local: &[(String, PathBuf)], output: &Path) -> RResult<()> {
+    let mut zip = ZipWriter::new(File::create(output)?);
+    zip.start_file("modrinth.index.json", FileOptions::default())?;
+    zip.write_all(serde_json::to_string_pretty(&index)?.as_bytes())?;
+
+    let folders = [
+        (pack.overrides_dirs(), "overrides"),
+        (pack.client_overrides_dirs(), "client-overrides"),
+        (pack.server_overrides_dirs(), "server-overrides")
+    ];
+    for (dirs, folder) in folders {
+        zip_dirs(&mut zip, &dirs, folder)?;
+    }
+
+    for (name, source) in local {
+        zip.start_file(name, FileOptions::default())?;
+        zip.write_all(&std::fs::read(source).map_err(|e| format!("can't read {}: {}", source.display(), e))?)?;
+    }
+
+    zip.finish()?;
+
+    Ok(())
+}
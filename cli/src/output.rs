@@ -0,0 +1,39 @@
+use {
+    std::{fmt::Display, sync::atomic::{AtomicBool, Ordering}},
+    serde::Serialize,
+    colored::*
+};
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+// Switches output of every command to JSON, colors are disabled
+pub fn set_json_output(json: bool) {
+    JSON_OUTPUT.store(json, Ordering::Relaxed);
+    if json {
+        colored::control::set_override(false);
+    }
+}
+
+#[inline(always)]
+pub fn json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::Relaxed)
+}
+
+pub fn print_json<T: Serialize>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(r) => println!("{}", r),
+        Err(e) => {
+            eprintln!("Failed to serialize output: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Prints message for humans, with JSON output it goes to stderr so stdout stays parseable
+pub fn print_message(message: impl Display) {
+    if json_output() { eprintln!("{}", message); } else { println!("{}", message); }
+}
+
+pub fn print_warning(message: impl Display) {
+    if json_output() { eprintln!("{}", message); } else { println!("{} {}", "Warning:".yellow(), message); }
+}
@@ -0,0 +1,341 @@
+use {
+    std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf, Component}
+    },
+    serde::{Serialize, Deserialize},
+
+    curseforge::prelude::*,
+    crate::{modpack::*, lock::*, config::merged_files, curseforge_pack::sanitize_pack_name}
+};
+
+// packwiz `pack.toml`
+#[derive(Serialize, Deserialize)]
+pub struct PackwizPack {
+    pub name: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
+    #[serde(rename = "pack-format", default = "default_pack_format")]
+    pub pack_format: String,
+
+    pub index: PackwizIndexRef,
+
+    // minecraft, forge, neoforge, fabric or quilt => version
+    pub versions: BTreeMap<String, String>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PackwizIndexRef {
+    pub file: String,
+
+    #[serde(rename = "hash-format")]
+    pub hash_format: String,
+
+    pub hash: String
+}
+
+// packwiz `index.toml`
+#[derive(Serialize, Deserialize)]
+pub struct PackwizIndex {
+    #[serde(rename = "hash-format")]
+    pub hash_format: String,
+
+    #[serde(default)]
+    pub files: Vec<PackwizIndexFile>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PackwizIndexFile {
+    pub file: String,
+    pub hash: String,
+
+    #[serde(rename = "hash-format", default, skip_serializing_if = "Option::is_none")]
+    pub hash_format: Option<String>,
+
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub metafile: bool
+}
+
+// packwiz `.pw.toml` mod metadata
+#[derive(Serialize, Deserialize)]
+pub struct PackwizMod {
+    pub name: String,
+    pub filename: String,
+
+    #[serde(default = "default_side")]
+    pub side: String,
+
+    pub download: PackwizDownload,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update: Option<PackwizUpdate>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PackwizDownload {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+
+    #[serde(rename = "hash-format")]
+    pub hash_format: String,
+
+    pub hash: String,
+
+    // metadata:curseforge for CurseForge files without direct url
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PackwizUpdate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub curseforge: Option<PackwizCurseForge>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modrinth: Option<PackwizModrinth>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PackwizCurseForge {
+    #[serde(rename = "file-id")]
+    pub file_id: usize,
+
+    #[serde(rename = "project-id")]
+    pub project_id: usize
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PackwizModrinth {
+    #[serde(rename = "mod-id")]
+    pub mod_id: String,
+
+    pub version: String
+}
+
+pub struct PackwizExport {
+    pub dir: PathBuf,
+    pub loader_version: String
+}
+
+fn default_pack_format() -> String {
+    "packwiz:1.1.0".to_string()
+}
+
+fn default_side() -> String {
+    "both".to_string()
+}
+
+fn is_false(v: &bool) -> bool {
+    !*v
+}
+
+fn read_toml<T: serde::de::DeserializeOwned>(path: &Path) -> RResult<T> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+
+    Ok( toml::from_str(&contents).map_err(|e| format!("{}: {}", path.display(), e))? )
+}
+
+// Hashes of unknown formats fail instead of passing unchecked, murmur2 is CurseForge fingerprint in decimal
+fn check_hash(path: &Path, format: &str, hash: &str) -> RResult<()> {
+    let data = std::fs::read(path)?;
+    let actual = match format {
+        "murmur2" => curseforge_fingerprint(&data).to_string(),
+        _ => hash_hex(format, &data)
+            .ok_or(format!("unsupported hash format {} of {}", format, path.display()))?
+    };
+
+    if actual != hash.to_lowercase() {
+        return Err(format!("hash mismatch of {}: expected {}, got {}", path.display(), hash, actual).into());
+    }
+
+    Ok(())
+}
+
+// Index paths come from the repository and must stay inside of it
+fn safe_path(relative: &str) -> RResult<&Path> {
+    let path = Path::new(relative);
+    if path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+        Ok(path)
+    } else {
+        Err(format!("{} points outside of packwiz repository", relative).into())
+    }
+}
+
+impl PackwizMod {
+    // Entry of `kind`, taken from the directory metadata file is in
+    pub fn to_modpack_mod(&self, kind: ProjectKind) -> Option<ModpackMod> {
+        let update = self.update.as_ref();
+        let mut mod_ = if let Some(cf) = update.and_then(|u| u.curseforge.as_ref()) {
+            let mut mod_ = ModpackMod::with_id(cf.project_id);
+            mod_.file = Some(cf.file_id);
+            mod_
+        } else {
+            ModpackMod::with_url(self.download.url.clone()?)
+        };
+
+        mod_.name = Some(self.name.clone());
+        mod_.kind = kind;
+        mod_.side = Some(Side::from_packwiz(&self.side)).filter(|s| *s != Side::Both);
+        Some(mod_)
+    }
+}
+
+// Reads packwiz repository into pinned modpack stored at `output`,
+// files that aren't mod metadata are copied into overrides next to it
+pub fn import_packwiz(dir: &str, output: Option<&String>) -> RResult<ModpackCfg> {
+    let root = Path::new(dir);
+    let pack: PackwizPack = read_toml(&root.join("pack.toml"))?;
+    let index_path = root.join(safe_path(&pack.index.file)?);
+    check_hash(&index_path, &pack.index.hash_format, &pack.index.hash)?;
+    let index: PackwizIndex = read_toml(&index_path)?;
+
+    let output = match output {
+        Some(o) => PathBuf::from(o),
+        None => PathBuf::from(sanitize_pack_name(&pack.name) + ".yaml")
+    };
+
+    let version = pack.versions.get("minecraft").cloned()
+        .ok_or("pack.toml has no minecraft version")?;
+    let (loader, loader_version) = pack.versions.iter().find(|(k, _)| *k != "minecraft")
+        .map(|(k, v)| (ModLoader::parse(k), Some(v.clone())))
+        .unwrap_or_default();
+    let mut result = ModpackCfg::new(sanitize_pack_name(&pack.name), version, loader,
+                                     output.to_string_lossy().to_string());
+    result.mc.loader_version = loader_version;
+
+    let overrides = format!("{}-overrides", output.file_stem().unwrap().to_string_lossy());
+    let overrides_dir = output.parent().unwrap_or_else(|| Path::new("")).join(&overrides);
+    let index_dir = index_path.parent().unwrap();
+
+    for file in &index.files {
+        let relative = safe_path(&file.file)?;
+        let path = index_dir.join(relative);
+        let format = file.hash_format.as_ref().unwrap_or(&index.hash_format);
+        check_hash(&path, format, &file.hash)?;
+
+        if file.metafile || file.file.ends_with(".pw.toml") {
+            // resourcepacks/*.pw.toml and others are installed into their own directories
+            let kind = relative.components().next()
+                .and_then(|c| c.as_os_str().to_str())
+                .and_then(ProjectKind::from_dir)
+                .unwrap_or_default();
+            let meta: PackwizMod = read_toml(&path)?;
+            match meta.to_modpack_mod(kind) {
+                Some(mod_) => result.mc.mods.push(mod_),
+                None => { return Err(format!("{} has no download source", file.file).into()); }
+            }
+            continue;
+        }
+
+        let target = overrides_dir.join(relative);
+        std::fs::create_dir_all(target.parent().unwrap())?;
+        std::fs::copy(&path, &target)?;
+        result.mc.overrides = Some(overrides.clone());
+    }
+
+    result.store();
+
+    Ok(result)
+}
+
+fn write_file(root: &Path, relative: &str, contents: &[u8],
+              index: &mut PackwizIndex, metafile: bool) -> RResult<()> {
+    let path = root.join(relative);
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    std::fs::write(&path, contents)?;
+
+    index.files.push(PackwizIndexFile{
+        file: relative.to_string(),
+        hash: sha256_hex(contents),
+        hash_format: None,
+        metafile
+    });
+
+    Ok(())
+}
+
+// Writes downloaded modpack as packwiz repository, mods are taken from the lock file
+pub fn export_packwiz(pack: &ModpackCfg, opts: &PackwizExport) -> RResult<()> {
+    let lock = ModpackLock::load(&pack.lock_file())
+        .ok_or(format!("{} has no lock file, download modpack first", pack.file))?;
+    let root = &opts.dir;
+    let mut index = PackwizIndex{hash_format: "sha256".to_string(), files: vec![]};
+
+    for locked in &lock.files {
+        // Local files are committed to the repository as is
+        if let Some(source) = &locked.source {
+            write_file(root, &locked.path, &std::fs::read(pack.pack_path(source))?, &mut index, false)?;
+            continue;
+        }
+
+        let path = Path::new(&locked.path);
+        let filename = path.file_name().unwrap().to_string_lossy().to_string();
+        let stem = path.file_stem().unwrap().to_string_lossy().to_string();
+        let mod_ = pack.mc.find_entry(locked.id, Some(&locked.url));
+        let name = match mod_ {
+            Some(m) => m.display_name(),
+            None => stem.clone()
+        };
+
+        let meta = PackwizMod{
+            name, filename: filename.clone(),
+            side: mod_.map(|m| m.side()).unwrap_or_default().to_string(),
+            download: PackwizDownload{
+                url: Some(locked.url.clone()),
+                hash_format: "sha1".to_string(),
+                hash: locked.sha1.clone(),
+                mode: None
+            },
+            update: match (locked.id, locked.file) {
+                (Some(project_id), Some(file_id)) => Some(PackwizUpdate{
+                    curseforge: Some(PackwizCurseForge{file_id, project_id}),
+                    modrinth: None
+                }),
+                _ => None
+            }
+        };
+
+        // Metadata goes into directory of entry kind, e.g. resourcepacks, where packwiz installs it
+        let dir = match mod_ {
+            Some(m) => m.kind.dir().to_string(),
+            None => path.parent().unwrap().to_string_lossy().to_string()
+        };
+        let stem = stem.to_lowercase().replace(' ', "-");
+        write_file(root, &format!("{}/{}.pw.toml", dir, stem),
+                   toml::to_string(&meta)?.as_bytes(), &mut index, true)?;
+    }
+
+    for (relative, file) in merged_files(&pack.overrides_dirs())? {
+        write_file(root, &relative, &std::fs::read(file)?, &mut index, false)?;
+    }
+
+    index.files.sort_by(|a, b| a.file.cmp(&b.file));
+    let index_contents = toml::to_string(&index)?;
+    std::fs::write(root.join("index.toml"), &index_contents)?;
+
+    let mut versions = BTreeMap::new();
+    versions.insert("minecraft".to_string(), pack.mc.exact_version()?);
+    versions.insert(pack.mc.loader.to_string(), opts.loader_version.clone());
+
+    let packwiz = PackwizPack{
+        name: pack.mc.name.clone(),
+        author: None,
+        version: None,
+        pack_format: default_pack_format(),
+        index: PackwizIndexRef{
+            file: "index.toml".to_string(),
+            hash_format: "sha256".to_string(),
+            hash: sha256_hex(index_contents.as_bytes())
+        },
+        versions
+    };
+    std::fs::write(root.join("pack.toml"), toml::to_string(&packwiz)?)?;
+
+    Ok(())
+}
@@ -1,6 +1,20 @@
 pub use crate::cli::*;
 pub use crate::config::*;
 pub use crate::modpack::*;
+pub use crate::output::*;
+pub use crate::lock::*;
+pub use crate::curseforge_pack::*;
+pub use crate::mrpack::*;
+pub use crate::packwiz::*;
+pub use crate::prism::*;
+pub use crate::launcher::*;
+pub use crate::instance::*;
+pub use crate::server_pack::*;
+pub use crate::loader_check::*;
+pub use crate::migrate::*;
+pub use crate::check::*;
+pub use crate::import_dir::*;
+pub use crate::inheritance::*;
 
 pub use curseforge::prelude::*;
 
@@ -0,0 +1,237 @@
+use {
+    std::path::{Path, PathBuf},
+    serde::{Serialize, Deserialize},
+
+    curseforge::prelude::*,
+    crate::{modpack::*, config::*, lock::ModpackLock, curseforge_pack::sanitize_pack_name, instance::instance_dir,
+            import_dir::{scan_jars, import_jars}}
+};
+
+// MultiMC / Prism Launcher `mmc-pack.json`
+#[derive(Serialize, Deserialize)]
+pub struct MmcPack {
+    #[serde(rename = "formatVersion")]
+    pub format_version: usize,
+
+    pub components: Vec<MmcComponent>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MmcComponent {
+    pub uid: String,
+    pub version: String,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub important: bool
+}
+
+const MINECRAFT_UID: &str = "net.minecraft";
+const INTERMEDIARY_UID: &str = "net.fabricmc.intermediary";
+
+// Component uid of mod loader
+pub fn loader_uid(loader: &ModLoader) -> Option<&'static str> {
+    match loader {
+        ModLoader::Forge => Some("net.minecraftforge"),
+        ModLoader::NeoForge => Some("net.neoforged"),
+        ModLoader::Fabric => Some("net.fabricmc.fabric-loader"),
+        ModLoader::Quilt => Some("org.quiltmc.quilt-loader"),
+        ModLoader::LiteLoader => Some("com.mumfrey.liteloader"),
+        _ => None
+    }
+}
+
+// Mod loader by component uid
+pub fn uid_loader(uid: &str) -> Option<ModLoader> {
+    match uid {
+        "net.minecraftforge" => Some(ModLoader::Forge),
+        "net.neoforged" => Some(ModLoader::NeoForge),
+        "net.fabricmc.fabric-loader" => Some(ModLoader::Fabric),
+        "org.quiltmc.quilt-loader" => Some(ModLoader::Quilt),
+        "com.mumfrey.liteloader" => Some(ModLoader::LiteLoader),
+        _ => None
+    }
+}
+
+impl MmcPack {
+    pub fn new(version: &str, loader: &ModLoader, loader_version: &str) -> RResult<MmcPack> {
+        let uid = loader_uid(loader)
+            .ok_or(format!("mod loader {} is not supported by Prism Launcher", loader))?;
+
+        let mut components = vec![
+            MmcComponent{uid: MINECRAFT_UID.to_string(), version: version.to_string(), important: true}
+        ];
+        if uid.starts_with("net.fabricmc") || uid.starts_with("org.quiltmc") {
+            components.push(MmcComponent{uid: INTERMEDIARY_UID.to_string(),
+                                         version: version.to_string(), important: false});
+        }
+        components.push(MmcComponent{uid: uid.to_string(), version: loader_version.to_string(),
+                                     important: false});
+
+        Ok( MmcPack{format_version: 1, components} )
+    }
+
+    pub fn component(&self, uid: &str) -> Option<&MmcComponent> {
+        self.components.iter().find(|c| c.uid == uid)
+    }
+
+    // Mod loader as (loader, loader version)
+    pub fn loader(&self) -> Option<(ModLoader, String)> {
+        self.components.iter().find_map(
+            |c| uid_loader(&c.uid).map(|l| (l, c.version.clone()))
+        )
+    }
+}
+
+fn instance_cfg(name: &str) -> String {
+    format!("[General]\nConfigVersion=1.2\nInstanceType=OneSix\niconKey=default\nname={}\n", name)
+}
+
+// Value of `key` in instance.cfg
+fn instance_cfg_value(cfg: &str, key: &str) -> Option<String> {
+    cfg.lines().find_map(|line| {
+        let (k, v) = line.split_once('=')?;
+        if k.trim() == key { Some(v.trim().to_string()) } else { None }
+    })
+}
+
+// Hardlinks file, copies it when hardlinks aren't possible (e.g. different disks)
+pub fn link_or_copy(src: &Path, dst: &Path) -> std::io::Result<()> {
+    if dst.exists() {
+        std::fs::remove_file(dst)?;
+    }
+
+    if std::fs::hard_link(src, dst).is_err() {
+        std::fs::copy(src, dst)?;
+    }
+
+    Ok(())
+}
+
+// Creates or updates Prism Launcher instance of downloaded modpack with client files of its lock file
+// and overrides, returns instance directory
+pub fn export_prism(pack: &ModpackCfg, instances: &Path, loader_version: &str) -> RResult<PathBuf> {
+    let installed = get_config_location().join("modpacks").join(&pack.mc.name);
+    if !installed.exists() {
+        return Err(format!("{} is not downloaded yet", pack.mc.name).into());
+    }
+
+    let lock = ModpackLock::load(&pack.lock_file())
+        .ok_or(format!("{} has no lock file, download modpack first", pack.file))?;
+
+    let instance = instances.join(sanitize_pack_name(&pack.mc.name));
+    let minecraft = instance.join(".minecraft");
+    let mods = minecraft.join("mods");
+    std::fs::create_dir_all(&instance)?;
+
+    let mmc = MmcPack::new(&pack.mc.exact_version()?, &pack.mc.loader, loader_version)?;
+    std::fs::write(instance.join("mmc-pack.json"), serde_json::to_string_pretty(&mmc)?)?;
+
+    // Launcher keeps instance settings (memory, java, etc.) there
+    if !instance.join("instance.cfg").exists() {
+        std::fs::write(instance.join("instance.cfg"), instance_cfg(&pack.mc.name))?;
+    }
+
+    // Mods are linked anew, files of other directories are replaced one by one
+    if mods.exists() {
+        std::fs::remove_dir_all(&mods)?;
+    }
+    std::fs::create_dir_all(&mods)?;
+
+    // Lock file lists every installed file: mods are in the modpack directory, other kinds in its instance
+    for locked in &lock.files {
+        let entry = match &locked.source {
+            Some(source) => pack.find_source(&pack.pack_path(source).to_string_lossy()),
+            None => pack.mc.find_entry(locked.id, Some(&locked.url))
+        };
+        if entry.map(|m| !m.installed_on(Side::Client)).unwrap_or(false) {
+            continue;
+        }
+
+        let source = match locked.path.strip_prefix("mods/") {
+            Some(file) => installed.join(file),
+            None => instance_dir(&pack.mc.name).join(&locked.path)
+        };
+        let target = minecraft.join(&locked.path);
+        std::fs::create_dir_all(target.parent().unwrap())?;
+
+        // Extracted worlds are copied once, saved progress in the instance is kept
+        if source.is_dir() {
+            if !target.exists() {
+                copy_dir(&source, &target)?;
+            }
+        } else if source.exists() {
+            link_or_copy(&source, &target)?;
+        } else {
+            return Err(format!("{} is not downloaded yet", locked.path).into());
+        }
+    }
+
+    for overrides in pack.overrides_dirs().iter().chain(&pack.client_overrides_dirs()) {
+        copy_dir(overrides, &minecraft)?;
+    }
+
+    Ok(instance)
+}
+
+// Reads Prism Launcher instance into pinned modpack stored at `output`, jars are matched by hashes
+// like `import dir` does, the rest of instance game directory becomes overrides next to it
+pub async fn import_prism(instance: &str, output: Option<&String>, cf: &CurseForge) -> RResult<ModpackCfg> {
+    let instance = Path::new(instance);
+    let mmc: MmcPack = serde_json::from_str(&std::fs::read_to_string(instance.join("mmc-pack.json"))?)?;
+    let cfg = std::fs::read_to_string(instance.join("instance.cfg")).unwrap_or_default();
+
+    let name = instance_cfg_value(&cfg, "name").unwrap_or_else(
+        || instance.file_name().unwrap().to_string_lossy().to_string()
+    );
+    let version = mmc.component(MINECRAFT_UID).map(|c| c.version.clone())
+        .ok_or("mmc-pack.json has no net.minecraft component")?;
+    let loader = mmc.loader().map(|l| l.0).unwrap_or_default();
+
+    let output = match output {
+        Some(o) => PathBuf::from(o),
+        None => PathBuf::from(sanitize_pack_name(&name) + ".yaml")
+    };
+    let mut pack = ModpackCfg::new(sanitize_pack_name(&name), version, loader,
+                                   output.to_string_lossy().to_string());
+    pack.mc.loader_version = mmc.loader().map(|l| l.1);
+    let mut lock = ModpackLock::new(pack.lock_file());
+
+    let minecraft = [".minecraft", "minecraft"].iter().map(|d| instance.join(d)).find(|d| d.exists());
+    if let Some(minecraft) = minecraft {
+        let overrides = format!("{}-overrides", output.file_stem().unwrap().to_string_lossy());
+        let overrides_dir = output.parent().unwrap_or_else(|| Path::new("")).join(&overrides);
+
+        let mods = minecraft.join("mods");
+        if mods.exists() {
+            let jars = scan_jars(&mods)?;
+            import_jars(&mut pack, &mut lock, &jars, cf).await?;
+
+            // Everything but jars, e.g. configs some mods keep in `mods`
+            for entry in std::fs::read_dir(&mods)? {
+                let entry = entry?;
+                let target = overrides_dir.join("mods").join(entry.file_name());
+                if entry.file_type()?.is_dir() {
+                    copy_dir(&entry.path(), &target)?;
+                } else if !jars.iter().any(|j| j.path == entry.path()) {
+                    std::fs::create_dir_all(target.parent().unwrap())?;
+                    std::fs::copy(entry.path(), &target)?;
+                } else {
+                    continue;
+                }
+                pack.mc.overrides = Some(overrides.clone());
+            }
+        }
+
+        for dir in ["config", "resourcepacks", "shaderpacks", "kubejs", "scripts"] {
+            if minecraft.join(dir).exists() {
+                copy_dir(&minecraft.join(dir), &overrides_dir.join(dir))?;
+                pack.mc.overrides = Some(overrides.clone());
+            }
+        }
+    }
+
+    pack.store();
+    lock.store();
+
+    Ok(pack)
+}
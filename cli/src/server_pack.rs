@@ -0,0 +1,97 @@
+use {
+    std::path::Path,
+    serde::Serialize,
+
+    curseforge::prelude::*,
+    crate::{modpack::*, lock::ModpackLock, config::copy_dir, instance::scan_overrides}
+};
+
+#[derive(Serialize)]
+pub struct ServerPackReport {
+    pub download: DownloadReport,
+
+    // Entries left out because they are needed only on client
+    pub skipped: Vec<String>,
+
+    // Requirements excluded or replaced by the modpack
+    pub notes: Vec<ResolveNote>
+}
+
+// Installs server side mods of modpack with its overrides and server overrides into `dir`
+pub async fn build_server_pack(pack: &ModpackCfg, cf: &CurseForge,
+                               dir: &Path, quiet: bool, scan: ScanMode) -> RResult<ServerPackReport> {
+    let version = pack.mc.game_version();
+    let mods = dir.join("mods");
+    std::fs::create_dir_all(&mods)?;
+
+    let mut skipped = vec![];
+    for mod_ in &pack.mc.mods {
+        if !mod_.installed_on(Side::Server) || !server_kind(mod_.kind) {
+            skipped.push(mod_.display_name());
+        }
+    }
+
+    let roots = pack.mc.pinned_ids_for(Side::Server).into_iter()
+        .filter(|(id, _)| server_kind(pack.mc.kind_of(*id)))
+        .collect();
+    let deps = resolve_dependencies(cf, roots, version.clone(),
+                                    mods.to_string_lossy().to_string(), quiet,
+                                    &pack.mc.resolve_rules()).await;
+
+    let mut downloader = MassDownloader::new().with_quiet(quiet).with_scan(scan);
+    for mut target in deps.targets {
+        let id = target.id.unwrap();
+        target.required = !pack.mc.is_optional(id);
+        downloader.add_target(server_target(target, pack.mc.kind_of(id), dir));
+    }
+
+    let lock = ModpackLock::load(&pack.lock_file());
+    for mod_ in &pack.mc.mods {
+        if mod_.id.is_some() || !mod_.installed_on(Side::Server) || !server_kind(mod_.kind) {
+            continue;
+        }
+
+        if let Some(target) = pack.direct_target(mod_, mods.to_string_lossy().to_string(), lock.as_ref()) {
+            downloader.add_target(server_target(target, mod_.kind, dir));
+        }
+    }
+
+    let download = downloader.download(cf, version).await;
+    if let Some(failed) = download.failed().find(|e| e.required) {
+        return Err(format!("failed to download {}", failed.name()).into());
+    }
+
+    // Dependencies and mods without explicit side are checked by their jars
+    for entry in download.entries.iter().filter(|e| !e.is_failed() && e.kind.is_mod()) {
+        let explicit = match &entry.source {
+            Some(source) => pack.find_source(source),
+            None => pack.mc.find_entry(entry.id, entry.url.as_ref())
+        }.and_then(|m| m.side);
+        let path = Path::new(entry.path.as_ref().unwrap());
+
+        if explicit.is_none() && Side::from_jar(path) == Some(Side::Client) {
+            std::fs::remove_file(path)?;
+            skipped.push(path.file_name().unwrap().to_string_lossy().to_string());
+        }
+    }
+
+    for overrides in pack.overrides_dirs().iter().chain(&pack.server_overrides_dirs()) {
+        scan_overrides(overrides, scan)?;
+        copy_dir(overrides, dir)?;
+    }
+
+    Ok(ServerPackReport{download, skipped, notes: deps.notes})
+}
+
+// Resource packs, shader packs and saves are useless on dedicated server
+fn server_kind(kind: ProjectKind) -> bool {
+    matches!(kind, ProjectKind::Mod | ProjectKind::DataPack)
+}
+
+// Server loads data packs only from its world
+fn server_target(target: DownloadTarget, kind: ProjectKind, dir: &Path) -> DownloadTarget {
+    match kind {
+        ProjectKind::DataPack => target.with_kind(kind, &dir.join("world")),
+        _ => target.with_kind(kind, dir)
+    }
+}
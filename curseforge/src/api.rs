@@ -1,7 +1,7 @@
 use {
     hyper::{
         Client, client::HttpConnector,
-        Response
+        Request, Response, Body
     },
     urlencoding::encode,
     hyper_tls::HttpsConnector,
@@ -26,7 +26,7 @@ async fn web_request(http: &Client<HttpsConnector<HttpConnector>>, url: &str) ->
         if response.as_ref().unwrap().status() == 200 {
             break;
         }
-        println!("Retrying...");
+        eprintln!("Retrying...");
 
         retries -= 1;
     }
@@ -38,9 +38,57 @@ async fn web_request(http: &Client<HttpsConnector<HttpConnector>>, url: &str) ->
     Ok( response.unwrap() )
 }
 
+pub(crate) async fn web_post(http: &Client<HttpsConnector<HttpConnector>>, url: &str,
+                      body: String) -> RResult<Response<hyper::Body>> {
+    let request = Request::post(url)
+        .header("Content-Type", "application/json")
+        .header("User-Agent", concat!("mcget/", env!("CARGO_PKG_VERSION")))
+        .body(Body::from(body))?;
+    let response = http.request(request).await?;
+    if response.status() != 200 {
+        return Err(format!("{} returned {}", url, response.status()).into());
+    }
+
+    Ok( response )
+}
+
+pub(crate) const MAX_REDIRECTS: usize = 10;
+
+// Body of GET request, up to `MAX_REDIRECTS` redirects are followed
+pub(crate) async fn web_get(http: &Client<HttpsConnector<HttpConnector>>, url: &str) -> RResult<Vec<u8>> {
+    let mut url = url.to_string();
+    for _ in 0..=MAX_REDIRECTS {
+        let uri: hyper::Uri = url.parse()?;
+        let response = http.get(uri.clone()).await?;
+        if let Some(location) = response.headers().get("location") {
+            let location = location.to_str()?;
+            url = match (location.starts_with('/'), uri.scheme_str(), uri.authority()) {
+                (true, Some(scheme), Some(authority)) => format!("{}://{}{}", scheme, authority, location),
+                _ => location.to_string()
+            };
+            continue;
+        }
+
+        if response.status() != 200 {
+            return Err(format!("{} responded with {}", url, response.status()).into());
+        }
+
+        return Ok(hyper::body::to_bytes(response).await?.to_vec());
+    }
+
+    Err(format!("{} redirects too many times", url).into())
+}
+
+// Downloads file at `url` into memory
+pub async fn fetch_url(url: &str) -> RResult<Vec<u8>> {
+    let client = Client::builder().build::<_, hyper::Body>(HttpsConnector::new());
+    web_get(&client, url).await
+}
+
 impl CurseForge {
-    pub async fn search(&self, query: &str, version: Option<&String>) -> RResult<Vec<Mod>> {
-        let mut url = Self::root_url("search", true);
+    pub async fn search(&self, query: &str, version: Option<&String>,
+                        kind: ProjectKind) -> RResult<Vec<Mod>> {
+        let mut url = Self::root_url("search", Some(kind));
         url.push_str("searchFilter=");
         url.push_str((&encode(query)).as_ref());
 
@@ -57,7 +105,7 @@ impl CurseForge {
     }
 
     pub async fn files(&self, id: usize, game: GameVersion) -> RResult<Vec<ModFile>> {
-        let mut url = Self::root_url(&id.to_string(), false);
+        let mut url = Self::root_url(&id.to_string(), None);
         url.push_str("/files");
 
         let response = web_request(&self.client, &url).await?;
@@ -66,16 +114,58 @@ impl CurseForge {
 
         let resp: Vec<ModFile> = serde_json::from_str(body).unwrap();
 
-        Ok( resp.where_mods(&game.version, game.mod_loader.as_ref()) )
+        Ok( resp.into_iter().filter(|file| game.matches(file)).collect() )
+    }
+
+    pub async fn mod_info(&self, id: usize) -> RResult<Mod> {
+        let url = Self::root_url(&id.to_string(), None);
+
+        let response = web_request(&self.client, &url).await?;
+        let b_bytes = hyper::body::to_bytes(response.into_body()).await?;
+
+        Ok( serde_json::from_slice(&b_bytes)? )
+    }
+
+    pub async fn file(&self, id: usize, file_id: usize) -> RResult<ModFile> {
+        let mut url = Self::root_url(&id.to_string(), None);
+        url.push_str("/file/");
+        url.push_str(&file_id.to_string());
+
+        let response = web_request(&self.client, &url).await?;
+        let b_bytes = hyper::body::to_bytes(response.into_body()).await?;
+
+        Ok( serde_json::from_slice(&b_bytes)? )
+    }
+
+    // Files matching CurseForge fingerprints, see `curseforge_fingerprint`
+    pub async fn fingerprints(&self, fingerprints: &[u32]) -> RResult<Vec<FingerprintMatch>> {
+        let url = "https://addons-ecs.forgesvc.net/api/v2/fingerprint";
+
+        let response = web_post(&self.client, url, serde_json::to_string(fingerprints)?).await?;
+        let b_bytes = hyper::body::to_bytes(response.into_body()).await?;
+        let result: FingerprintResult = serde_json::from_slice(&b_bytes)?;
+
+        Ok( result.exact_matches )
+    }
+
+    // Pinned file if there is one, latest file for the game version otherwise
+    pub async fn select_file(&self, id: usize, pin: Option<usize>,
+                             game: GameVersion) -> RResult<Option<ModFile>> {
+        if let Some(file_id) = pin {
+            return Ok( Some(self.file(id, file_id).await?) );
+        }
+
+        let files = self.files(id, game).await?;
+        Ok( files.latest().ok().cloned() )
     }
 
     #[inline(always)]
-    fn root_url(path: &str, add_game: bool) -> String {
+    fn root_url(path: &str, section: Option<ProjectKind>) -> String {
         let mut url = "https://addons-ecs.forgesvc.net/api/v2/addon/".to_string();
         url.push_str(path);
 
-        if add_game {
-            url.push_str("?gameId=432&sectionId=6&");
+        if let Some(kind) = section {
+            url.push_str(&format!("?gameId=432&sectionId={}&", kind.section_id()));
         }
 
         url
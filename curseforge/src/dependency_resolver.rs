@@ -8,41 +8,189 @@ use {
     crate::{api::*, objects::*,
             downloader::DownloadTarget},
     std::{
-        collections::HashSet,
+        collections::{HashSet, HashMap, hash_map::Entry},
         sync::Arc,
     },
+    serde::Serialize,
     colored::*
 };
 
-#[async_recursion::async_recursion]
-async fn resolve_dependency(of: usize, cf: Arc<CurseForge>,
-                            deps: Sender<usize>, version: GameVersion,
-                            root: bool, resolved: Arc<Mutex<HashSet<usize>>>) {
-    let files;
-    match cf.files(of, version.clone()).await {
-        Ok(r) => { files = Option::Some(r); },
-        Err(_) => {
-            println!("{} to resolve dependency of modid {}", "Failed".red(), of);
-            files = None;
+// Pack rules applied to dependencies, mods resolved from the pack itself are kept as is
+#[derive(Clone, Default, Debug)]
+pub struct ResolveRules {
+    pub exclude: HashSet<usize>,
+
+    // Dependency id to id of the mod resolved instead of it
+    pub replace: HashMap<usize, usize>
+}
+
+impl ResolveRules {
+    // Id to resolve for required dependency `id` of `of`, None if it's excluded
+    fn apply(&self, id: usize, of: usize) -> (Option<usize>, Option<ResolveNote>) {
+        let required_by = vec![NoteMod::new(of)];
+        if self.exclude.contains(&id) {
+            return (None, Some(ResolveNote::Excluded{dependency: NoteMod::new(id), required_by}));
+        }
+
+        match self.replace.get(&id) {
+            Some(with) => (Some(*with), Some(ResolveNote::Replaced{dependency: NoteMod::new(id),
+                                                                   with: NoteMod::new(*with), required_by})),
+            None => (Some(id), None)
+        }
+    }
+
+    // Same as `apply` without the note
+    pub fn dependency(&self, id: usize) -> Option<usize> {
+        self.apply(id, 0).0
+    }
+}
+
+// Mod mentioned by a note, name is looked up once resolution is done
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct NoteMod {
+    pub id: usize,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>
+}
+
+impl NoteMod {
+    pub fn new(id: usize) -> NoteMod {
+        NoteMod{id, name: None}
+    }
+}
+
+impl std::fmt::Display for NoteMod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "{}", self.id)
+        }
+    }
+}
+
+// Requirement which wasn't resolved as is because of pack rules
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "note", rename_all = "lowercase")]
+pub enum ResolveNote {
+    Excluded { dependency: NoteMod, required_by: Vec<NoteMod> },
+    Replaced { dependency: NoteMod, with: NoteMod, required_by: Vec<NoteMod> }
+}
+
+impl ResolveNote {
+    pub fn id(&self) -> usize {
+        match self {
+            ResolveNote::Excluded{dependency, ..} | ResolveNote::Replaced{dependency, ..} => dependency.id
+        }
+    }
+
+    fn required_by(&mut self) -> &mut Vec<NoteMod> {
+        match self {
+            ResolveNote::Excluded{required_by, ..} | ResolveNote::Replaced{required_by, ..} => required_by
+        }
+    }
+
+    fn mods(&mut self) -> Vec<&mut NoteMod> {
+        match self {
+            ResolveNote::Excluded{dependency, required_by} =>
+                std::iter::once(dependency).chain(required_by.iter_mut()).collect(),
+            ResolveNote::Replaced{dependency, with, required_by} =>
+                [dependency, with].into_iter().chain(required_by.iter_mut()).collect()
+        }
+    }
+}
+
+impl std::fmt::Display for ResolveNote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let joined = |mods: &[NoteMod]| mods.iter().map(|m| m.to_string()).collect::<Vec<String>>().join(", ");
+        match self {
+            ResolveNote::Excluded{dependency, required_by} =>
+                write!(f, "{} required by {} is excluded", dependency, joined(required_by)),
+            ResolveNote::Replaced{dependency, with, required_by} =>
+                write!(f, "{} required by {} is replaced with {}", dependency, joined(required_by), with)
+        }
+    }
+}
+
+// Every requirer of a dependency is kept in the note about it
+fn add_note(notes: &mut Vec<ResolveNote>, mut note: ResolveNote) {
+    let existing = match notes.iter_mut().find(|n| n.id() == note.id()) {
+        Some(existing) => existing,
+        None => {
+            notes.push(note);
+            return;
         }
     };
 
-    if files.is_none() || (files.is_some() && files.as_ref().unwrap().latest().is_err()) {
-        if root {
-            deps.send(0).await.unwrap_or_default();
+    for requirer in note.required_by().drain(..) {
+        if !existing.required_by().contains(&requirer) {
+            existing.required_by().push(requirer);
         }
+    }
+}
 
-        return;
+// Fills names of mods mentioned by notes, each mod is looked up once
+async fn name_notes(cf: &CurseForge, notes: &mut [ResolveNote]) {
+    let mut names: HashMap<usize, Option<String>> = HashMap::new();
+    for note in notes.iter_mut() {
+        for mod_ in note.mods() {
+            if let Entry::Vacant(entry) = names.entry(mod_.id) {
+                entry.insert(cf.mod_info(mod_.id).await.ok().map(|m| m.name));
+            }
+            mod_.name = names[&mod_.id].clone();
+        }
+        note.required_by().sort_by_key(|m| m.id);
     }
+}
 
-    let latest = files.as_ref().unwrap().latest().unwrap();
+// Resolved targets together with notes about applied rules
+#[derive(Default)]
+pub struct Resolution {
+    pub targets: Vec<DownloadTarget>,
+    pub notes: Vec<ResolveNote>
+}
+
+#[allow(clippy::too_many_arguments)]
+#[async_recursion::async_recursion]
+async fn resolve_dependency(of: usize, pin: Option<usize>, cf: Arc<CurseForge>,
+                            deps: Sender<usize>, version: GameVersion,
+                            root: bool, quiet: bool, resolved: Arc<Mutex<HashSet<usize>>>,
+                            rules: Arc<ResolveRules>, notes: Arc<Mutex<Vec<ResolveNote>>>) {
+    let selected = match cf.select_file(of, pin, version.clone()).await {
+        Ok(r) => r,
+        Err(_) => {
+            if !quiet {
+                println!("{} to resolve dependency of modid {}", "Failed".red(), of);
+            }
+            None
+        }
+    };
+
+    let latest = match selected {
+        Some(r) => r,
+        None => {
+            if root {
+                deps.send(0).await.unwrap_or_default();
+            }
+
+            return;
+        }
+    };
 
     let mut tasks = vec![];
     for dep in &latest.dependencies {
         let version = version.clone();
-        let addon_id = dep.addon_id;
         if dep.type_ != 3 { continue; }
 
+        let (addon_id, note) = rules.apply(dep.addon_id, of);
+        if let Some(note) = note {
+            add_note(&mut *notes.lock().await, note);
+        }
+        let addon_id = match addon_id {
+            Some(id) => id,
+            None => { continue; }
+        };
+
         {
             let lock = resolved.lock().await;
             if (*lock).contains(&addon_id) {
@@ -53,12 +201,14 @@ async fn resolve_dependency(of: usize, cf: Arc<CurseForge>,
         let cf = cf.clone();
         let rdeps = deps.clone();
         let resolved = resolved.clone();
+        let rules = rules.clone();
+        let notes = notes.clone();
 
         tasks.push(tokio::spawn(async move {
-            resolve_dependency(addon_id, cf, rdeps, version, false,
-                                resolved).await;
+            resolve_dependency(addon_id, None, cf, rdeps, version, false,
+                                quiet, resolved, rules, notes).await;
         }));
-        deps.send(dep.addon_id).await.unwrap_or_default();
+        deps.send(addon_id).await.unwrap_or_default();
     }
 
     for task in tasks {
@@ -70,25 +220,34 @@ async fn resolve_dependency(of: usize, cf: Arc<CurseForge>,
     }
 }
 
-pub async fn resolve_dependencies(cf: &CurseForge, of: Vec<usize>,
-                                  game: GameVersion, path: String) -> Vec<DownloadTarget> {
+// Resolves `of` (mod id, pinned file id) with all of their required dependencies,
+// excluded and replaced dependencies are listed in notes
+pub async fn resolve_dependencies(cf: &CurseForge, of: Vec<(usize, Option<usize>)>,
+                                  game: GameVersion, path: String, quiet: bool,
+                                  rules: &ResolveRules) -> Resolution {
     let mut targets = vec![];
     let dependency_map: Arc<Mutex<HashSet<usize>>> = Default::default();
+    let notes: Arc<Mutex<Vec<ResolveNote>>> = Default::default();
+    let rules = Arc::new(rules.clone());
     let (tx, mut rx) = channel(32);
     let cf = Arc::new(cf.clone());
     let mut tasks = vec![];
+    let pins: HashMap<usize, usize> = of.iter().filter_map(
+        |(id, pin)| pin.map(|p| (*id, p))
+    ).collect();
 
-    for dep in &of {
+    for (id, pin) in of {
         let tx = tx.clone();
         let cf = cf.clone();
         let ver = game.clone();
-        let id = dep.clone();
         let resolved = dependency_map.clone();
+        let rules = rules.clone();
+        let notes = notes.clone();
 
         tx.send(id).await.unwrap_or_default();
         tasks.push(tokio::spawn(async move {
-            resolve_dependency(id, cf, tx, ver, true,
-                                resolved).await;
+            resolve_dependency(id, pin, cf, tx, ver, true,
+                                quiet, resolved, rules, notes).await;
         }));
     }
 
@@ -99,13 +258,149 @@ pub async fn resolve_dependencies(cf: &CurseForge, of: Vec<usize>,
 
         let mut lock = dependency_map.lock().await;
         if !(*lock).contains(&modid) {
-            targets.push(DownloadTarget{id: Some(modid), dest: path.clone(),
-                                        url: None});
+            let mut target = DownloadTarget::with_id(modid, path.clone());
+            target.file = pins.get(&modid).cloned();
+            targets.push(target);
             (*lock).insert(modid);
 
-            println!(">> Resolved dependency {}", modid);
+            if !quiet {
+                println!(">> Resolved dependency {}", modid);
+            }
         }
     }
 
-    targets
-}
\ No newline at end of file
+    let mut notes = notes.lock().await.clone();
+    notes.sort_by_key(|n| n.id());
+    name_notes(&cf, &mut notes).await;
+    for note in notes.iter().filter(|_| !quiet) {
+        println!(">> Dependency {}", note);
+    }
+
+    Resolution{targets, notes}
+}
+
+#[derive(Serialize, Debug)]
+pub struct DependencyNode {
+    pub id: usize,
+    pub name: Option<String>,
+    pub file: Option<ModFile>,
+
+    // Dependency left out by pack rules, it has no file and dependencies
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub excluded: bool,
+
+    // Dependency this mod is resolved instead of by pack rules
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replaces: Option<NoteMod>,
+
+    pub dependencies: Vec<DependencyNode>
+}
+
+// Names and selected files of mods met on several branches are fetched once
+#[derive(Default)]
+pub struct TreeCache {
+    names: HashMap<usize, Option<String>>,
+
+    // By (mod id, pinned file id)
+    files: HashMap<(usize, Option<usize>), Option<ModFile>>
+}
+
+impl TreeCache {
+    async fn name(&mut self, cf: &CurseForge, id: usize) -> Option<String> {
+        if let Entry::Vacant(entry) = self.names.entry(id) {
+            entry.insert(cf.mod_info(id).await.ok().map(|m| m.name));
+        }
+
+        self.names[&id].clone()
+    }
+
+    async fn get(&mut self, cf: &CurseForge, id: usize, pin: Option<usize>,
+                 game: &GameVersion) -> (Option<String>, Option<ModFile>) {
+        if let Entry::Vacant(entry) = self.files.entry((id, pin)) {
+            entry.insert(cf.select_file(id, pin, game.clone()).await.unwrap_or(None));
+        }
+
+        (self.name(cf, id).await, self.files[&(id, pin)].clone())
+    }
+}
+
+// Builds tree of required dependencies of mod `of`, each branch stops on cycles.
+// Excluded dependencies are kept as leaves, replaced ones are marked on their replacements
+#[async_recursion::async_recursion]
+pub async fn dependency_tree(cf: &CurseForge, of: usize, pin: Option<usize>, game: GameVersion,
+                             rules: &ResolveRules, parents: &mut Vec<usize>,
+                             cache: &mut TreeCache) -> DependencyNode {
+    let (name, file) = cache.get(cf, of, pin, &game).await;
+
+    let mut node = DependencyNode{id: of, name, file: None, excluded: false, replaces: None, dependencies: vec![]};
+    let file = match file {
+        Some(f) => f,
+        None => { return node; }
+    };
+
+    parents.push(of);
+    for dep in file.dependencies.iter().filter(|d| d.type_ == 3) {
+        let id = match rules.dependency(dep.addon_id) {
+            Some(id) if !parents.contains(&id) => id,
+            Some(_) => { continue; }
+            None => {
+                let name = cache.name(cf, dep.addon_id).await;
+                node.dependencies.push(DependencyNode{id: dep.addon_id, name, file: None, excluded: true,
+                                                      replaces: None, dependencies: vec![]});
+                continue;
+            }
+        };
+
+        let mut child = dependency_tree(cf, id, None, game.clone(), rules, parents, cache).await;
+        if id != dep.addon_id {
+            child.replaces = Some(NoteMod{id: dep.addon_id, name: cache.name(cf, dep.addon_id).await});
+        }
+        node.dependencies.push(child);
+    }
+    parents.pop();
+
+    node.file = Some(file);
+    node
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> ResolveRules {
+        ResolveRules{exclude: HashSet::from([10]), replace: HashMap::from([(20, 21)])}
+    }
+
+    #[test]
+    fn notes_keep_every_requirer() {
+        let rules = rules();
+        let mut notes = vec![];
+        for of in [1, 2, 1] {
+            add_note(&mut notes, rules.apply(10, of).1.unwrap());
+        }
+
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0], ResolveNote::Excluded{dependency: NoteMod::new(10),
+                                                   required_by: vec![NoteMod::new(1), NoteMod::new(2)]});
+    }
+
+    #[test]
+    fn notes_show_names() {
+        let mut note = rules().apply(20, 1).1.unwrap();
+        assert_eq!(note.to_string(), "20 required by 1 is replaced with 21");
+
+        for (mod_, name) in note.mods().into_iter().zip(["Lib", "Lib Fork", "Mod"]) {
+            mod_.name = Some(name.to_string());
+        }
+        assert_eq!(note.to_string(), "Lib required by Mod is replaced with Lib Fork");
+    }
+
+    #[test]
+    fn rules_apply_only_to_listed_mods() {
+        let rules = rules();
+        assert_eq!(rules.dependency(10), None);
+        assert_eq!(rules.dependency(20), Some(21));
+        assert_eq!(rules.dependency(30), Some(30));
+        assert!(rules.apply(30, 1).1.is_none());
+    }
+}
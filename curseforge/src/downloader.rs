@@ -3,8 +3,9 @@ use {
         ProgressBar, HumanBytes
     },
 
-    crate::{api::CurseForge, objects::*},
+    crate::{api::{CurseForge, MAX_REDIRECTS}, objects::*, hash::*, scanner::*},
     std::path::Path,
+    serde::Serialize,
 
     hyper::{
         Client, client::HttpConnector,
@@ -19,14 +20,144 @@ use {
     colored::*
 };
 
-type ArcResult<T> = Result<T, Box<dyn std::error::Error + Send>>;
+// File name part of URL without query
+pub fn url_filename(url: &str) -> String {
+    let mut name = &url[url.rfind('/').map(|p| p + 1).unwrap_or(0)..];
+    if let Some(pos) = name.rfind('?') {
+        name = &name[..pos];
+    }
+
+    name.to_string()
+}
 
 #[derive(Clone, Debug)]
 pub struct DownloadTarget {
     pub id: Option<usize>,
     pub url: Option<String>,
 
+    // Pinned CurseForge file id, latest file is used if there is none
+    pub file: Option<usize>,
+
+    // Local file installed by hardlink or copy
+    pub path: Option<String>,
+
     pub dest: String,
+
+    // Existing file is kept only if it matches, usually taken from the lock file
+    pub expected: ExpectedFile,
+
+    // Failure of required target fails the whole installation
+    pub required: bool,
+
+    pub kind: ProjectKind
+}
+
+impl DownloadTarget {
+    pub fn with_id(id: usize, dest: String) -> DownloadTarget {
+        DownloadTarget{id: Some(id), url: None, file: None, path: None, dest, expected: Default::default(), required: true,
+                       kind: ProjectKind::Mod}
+    }
+
+    pub fn with_url(url: String, dest: String) -> DownloadTarget {
+        DownloadTarget{id: None, url: Some(url), file: None, path: None, dest, expected: Default::default(), required: true,
+                       kind: ProjectKind::Mod}
+    }
+
+    pub fn with_path(path: String, dest: String) -> DownloadTarget {
+        DownloadTarget{id: None, url: None, file: None, path: Some(path), dest, expected: Default::default(), required: true,
+                       kind: ProjectKind::Mod}
+    }
+
+    // Non-mod files go to their own directory under `root`, e.g. `root/resourcepacks`
+    pub fn with_kind(mut self, kind: ProjectKind, root: &Path) -> DownloadTarget {
+        self.kind = kind;
+        if !kind.is_mod() {
+            self.dest = root.join(kind.dir()).to_string_lossy().to_string();
+        }
+        self
+    }
+
+    // Mod id, URL or path of target
+    pub fn name(&self) -> String {
+        match (&self.id, &self.url, &self.path) {
+            (Some(id), _, _) => id.to_string(),
+            (None, Some(url), _) => url.clone(),
+            (None, None, Some(path)) => path.clone(),
+            (None, None, None) => "<unknown>".to_string()
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum DownloadStatus {
+    Downloaded,
+    Skipped,
+    Failed { error: String }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DownloadEntry {
+    pub id: Option<usize>,
+    pub file: Option<usize>,
+    pub required: bool,
+    pub kind: ProjectKind,
+
+    #[serde(flatten)]
+    pub status: DownloadStatus,
+
+    pub bytes: usize,
+    pub url: Option<String>,
+
+    // Local file the entry was installed from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+
+    pub path: Option<String>,
+    pub sha1: Option<String>,
+    pub sha512: Option<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<ScanFinding>
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct DownloadReport {
+    pub entries: Vec<DownloadEntry>,
+    pub bytes: usize
+}
+
+impl DownloadEntry {
+    fn new(target: &DownloadTarget) -> DownloadEntry {
+        DownloadEntry{id: target.id, file: target.file, required: target.required, kind: target.kind,
+                      status: DownloadStatus::Downloaded, bytes: 0,
+                      url: target.url.clone(), source: target.path.clone(), path: None,
+                      sha1: None, sha512: None, findings: vec![]}
+    }
+
+    pub fn is_failed(&self) -> bool {
+        matches!(self.status, DownloadStatus::Failed{..})
+    }
+
+    // Mod id, URL or local path of downloaded file
+    pub fn name(&self) -> String {
+        match (&self.id, &self.url, &self.source) {
+            (Some(id), _, _) => id.to_string(),
+            (None, Some(url), _) => url.clone(),
+            (None, None, Some(source)) => source.clone(),
+            (None, None, None) => "<unknown>".to_string()
+        }
+    }
+}
+
+impl DownloadReport {
+    pub fn failed(&self) -> impl Iterator<Item = &DownloadEntry> {
+        self.entries.iter().filter(|e| e.is_failed())
+    }
+
+    pub fn has_required_failures(&self) -> bool {
+        self.failed().any(|e| e.required)
+    }
 }
 
 // Mass mod downloader
@@ -34,92 +165,184 @@ pub struct MassDownloader {
     pub progress: Option<ProgressBar>,
 
     files: Vec<DownloadTarget>,
+    quiet: bool,
+    scan: ScanMode
 }
 
 impl MassDownloader {
     pub fn new() -> MassDownloader {
         MassDownloader{ progress: None,
-                        files: Default::default() }
+                        files: Default::default(),
+                        quiet: false,
+                        scan: Default::default() }
+    }
+
+    // Hides progress bar and messages
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    // Installed jars are scanned for malware, `Block` fails flagged ones
+    pub fn with_scan(mut self, scan: ScanMode) -> Self {
+        self.scan = scan;
+        self
     }
 
     pub fn add_file(&mut self, id: usize, dest: String) {
-        self.files.push(DownloadTarget{id: Some(id), dest, url: None});
+        self.files.push(DownloadTarget::with_id(id, dest));
     }
 
     pub fn add_target(&mut self, target: DownloadTarget) {
         self.files.push(target);
     }
 
+    // Hardlinks or copies local file, existing file is replaced if its content differs
+    fn install_local(source: &str, dest: &Path, entry: &mut DownloadEntry) -> Result<(), String> {
+        let data = std::fs::read(source).map_err(|e| format!("can't read {}: {}", source, e))?;
+        entry.bytes = data.len();
+        entry.sha1 = Some(sha1_hex(&data));
+        entry.sha512 = Some(sha512_hex(&data));
+
+        if dest.exists() {
+            let installed = std::fs::read(dest).map_err(|e| e.to_string())?;
+            if installed == data {
+                entry.status = DownloadStatus::Skipped;
+                return Ok(());
+            }
+            std::fs::remove_file(dest).map_err(|e| e.to_string())?;
+        }
+
+        if std::fs::hard_link(source, dest).is_err() {
+            std::fs::write(dest, &data).map_err(|e| format!("can't write {}: {}", dest.display(), e))?;
+        }
+
+        Ok(())
+    }
+
+    // Scans installed jar, blocked one is removed
+    fn scan_process(scan: ScanMode, entry: &mut DownloadEntry) -> Result<(), String> {
+        let path = match &entry.path {
+            Some(path) if path.ends_with(".jar") => Path::new(path),
+            _ => { return Ok(()); }
+        };
+
+        // Jar the scanner can't read is not trusted either
+        entry.findings = match scan_jar(path) {
+            Ok(findings) => findings,
+            Err(e) => {
+                if scan == ScanMode::Block {
+                    std::fs::remove_file(path).unwrap_or_default();
+                }
+                return Err(format!("malware scanner can't read jar: {}", e));
+            }
+        };
+        if scan == ScanMode::Block && !entry.findings.is_empty() {
+            std::fs::remove_file(path).unwrap_or_default();
+            return Err(format!("blocked by malware scanner: {}", entry.findings[0]));
+        }
+
+        Ok(())
+    }
+
     async fn download_process(client: Client<HttpsConnector<HttpConnector>>,
-                              file: &mut DownloadTarget, cf: CurseForge,
-                              version: GameVersion) -> ArcResult<(usize, String)> {
+                              file: &DownloadTarget, cf: CurseForge,
+                              version: GameVersion, entry: &mut DownloadEntry) -> Result<(), String> {
         let mut url: String;
+        let version = version.for_kind(file.kind);
+
         let filename: String;
+        let mut expected = file.expected.clone();
 
-        if file.url.is_none() {
-            let mf = match cf.files(file.id.unwrap(), version).await {
-                Ok(r) => r,
-                Err(_) => { return Ok((0, file.id.unwrap().to_string())); }
+        if let Some(id) = file.id {
+            let latest = match cf.select_file(id, file.file, version).await {
+                Ok(Some(r)) => r,
+                Ok(None) => { return Err("no file for this game version".to_string()); },
+                Err(e) => { return Err(e.to_string()); }
             };
 
-            let latest = mf.latest();
-            if latest.is_err() {
-                return Ok((0, file.id.unwrap().to_string()));
-            }
-    
-            url = latest.unwrap().download_url.clone();
-            filename = latest.as_ref().unwrap().filename.clone();
+            entry.file = Some(latest.id);
+            expected = ExpectedFile{size: latest.length, fingerprint: latest.fingerprint, ..Default::default()};
+            url = latest.download_url.clone();
+            filename = latest.filename.clone();
+        } else if let Some(target) = &file.url {
+            url = target.clone();
+            filename = url_filename(target);
+        } else if let Some(source) = &file.path {
+            let filename = Path::new(source).file_name().ok_or(format!("{} is not a file", source))?;
+            std::fs::create_dir_all(&file.dest).map_err(|e| format!("can't create {}: {}", file.dest, e))?;
+            let dest = Path::new(&file.dest).join(filename);
+            entry.path = Some(dest.to_string_lossy().to_string());
+
+            return Self::install_local(source, &dest, entry);
         } else {
-            url = file.url.as_ref().unwrap().clone();
-            let fm = file.url.as_ref().unwrap();
-            let mut fm = &fm[fm.rfind('/').unwrap()+1..];
+            return Err("neither mod id, url nor path is specified".to_string());
+        }
 
-            if let Some(pos) = fm.rfind('?') {
-                fm = &fm[..pos];
-            }
+        std::fs::create_dir_all(&file.dest).map_err(|e| format!("can't create {}: {}", file.dest, e))?;
+        let dest = Path::new(&file.dest).join(&filename);
+        entry.path = Some(dest.to_string_lossy().to_string());
+        entry.url = Some(url.clone());
 
-            filename = fm.to_string();
-        }
-        
-        let dst_path = Path::new(&file.dest);
-        file.dest = dst_path.join(&filename).to_str().unwrap().to_string();
+        // Existing file which can't be verified, truncated or changed one is downloaded again
+        if dest.exists() {
+            let data = std::fs::read(&dest).map_err(|e| e.to_string())?;
+            if expected.is_known() && expected.check(&data).is_ok() {
+                entry.status = DownloadStatus::Skipped;
+                entry.bytes = data.len();
+                entry.sha1 = Some(sha1_hex(&data));
+                entry.sha512 = Some(sha512_hex(&data));
 
-        let response;
+                return Ok(());
+            }
+        }
 
-        loop {
-            url = url.replace(" ", "%20");
-            let loc = match client.get(url.parse().unwrap()).await {
-                Ok(r) => r,
-                Err(_) => { println!("Get"); return Ok((0, url.clone())); }
-            };
+        // Redirect cycles end with an error instead of hanging
+        let mut response = None;
+        for _ in 0..=MAX_REDIRECTS {
+            url = url.replace(' ', "%20");
+            let uri = url.parse().map_err(|e: hyper::http::uri::InvalidUri| e.to_string())?;
+            let loc = client.get(uri).await.map_err(|e| e.to_string())?;
 
-            if loc.headers().contains_key("location") {
-                url = loc.headers().get("location").unwrap().to_str().unwrap().to_string();
+            if let Some(location) = loc.headers().get("location") {
+                url = location.to_str().map_err(|e| e.to_string())?.to_string();
                 continue;
             }
 
-            response = loc;
+            response = Some(loc);
             break;
         }
+        let response = response.ok_or(format!("{} redirects too many times", url))?;
 
-        let body = match hyper::body::to_bytes(response).await {
-            Ok(r) => r,
-            Err(_) => { println!("read"); return Ok((0, url.clone())); }
-        };
+        entry.url = Some(url.clone());
+        if response.status() != 200 {
+            return Err(format!("server responded with {}", response.status()));
+        }
 
-        match std::fs::write(&file.dest, &body) {
-            Ok(r) => r,
-            Err(_) => { println!("write {}", file.dest); return Ok((0, url.clone())); }
-        };
-        
-        Ok((body.len(), url))
+        let body = hyper::body::to_bytes(response).await.map_err(|e| e.to_string())?;
+
+        // Files known from the lock file must not change
+        if file.expected.is_known() {
+            file.expected.check(&body).map_err(|e| format!("checksum mismatch, {}", e))?;
+        }
+        std::fs::write(&dest, &body).map_err(|e| format!("can't write {}: {}", dest.display(), e))?;
+
+        entry.bytes = body.len();
+        entry.sha1 = Some(sha1_hex(&body));
+        entry.sha512 = Some(sha512_hex(&body));
+
+        Ok(())
     }
 
     pub async fn download(&mut self, cf: &CurseForge,
-                          version: GameVersion) {
+                          version: GameVersion) -> DownloadReport {
         let https = HttpsConnector::new();
         let client = Client::builder().build::<_, hyper::Body>(https);
-        self.progress = Option::Some(ProgressBar::new(self.files.len() as u64 + 1));
+        self.progress = Option::Some(if self.quiet {
+            ProgressBar::hidden()
+        } else {
+            ProgressBar::new(self.files.len() as u64 + 1)
+        });
 
         let bar = self.progress.as_ref().unwrap();
         bar.inc(1);
@@ -127,41 +350,112 @@ impl MassDownloader {
 
         for file in &self.files {
             let tx = tx.clone();
-            let mut file = file.clone();
+            let file = file.clone();
             let client = client.clone();
             let cf = cf.clone();
             let version = version.clone();
+            let scan = self.scan;
 
             tokio::spawn(async move {
-                match Self::download_process(client, &mut file, cf, version).await {
-                    Ok((size, url)) => {
-                        tx.send((size, url)).await.unwrap_or_default();
-                    },
-
-                    Err(_) => {
-                        tx.send((0usize, file.id.unwrap_or(0).to_string())).await.unwrap_or_default();
-                    }
+                let mut entry = DownloadEntry::new(&file);
+                let result = match Self::download_process(client, &file, cf, version, &mut entry).await {
+                    Ok(()) => Self::scan_process(scan, &mut entry),
+                    Err(error) => Err(error)
+                };
+                if let Err(error) = result {
+                    entry.status = DownloadStatus::Failed{error};
                 }
+
+                tx.send(entry).await.unwrap_or_default();
             });
         }
 
-        let mut downloaded = 0usize;
-        let mut down_size = 0usize;
-        while downloaded < self.files.len() {
-            let (size, url) = rx.recv().await.unwrap();
-            if size == 0 {
-                bar.println(format!("Failed to download {}", url));
-            } else {
-                bar.println(format!("Successfully downloaded {} {}", HumanBytes(size as u64).to_string(), url));
+        let mut report = DownloadReport::default();
+        while report.entries.len() < self.files.len() {
+            let entry = rx.recv().await.unwrap();
+            match &entry.status {
+                DownloadStatus::Downloaded if entry.source.is_some() => {
+                    bar.println(format!("Installed local {}", entry.source.as_ref().unwrap()));
+                },
+                DownloadStatus::Downloaded => {
+                    bar.println(format!("Successfully downloaded {} {}", HumanBytes(entry.bytes as u64),
+                                        entry.url.as_ref().unwrap()));
+                },
+                DownloadStatus::Skipped => {
+                    bar.println(format!("Already downloaded {}", entry.path.as_ref().unwrap()));
+                },
+                DownloadStatus::Failed{error} => {
+                    bar.println(format!("{} to download {}: {}", "Failed".red(), entry.name(), error));
+                }
+            }
+
+            if !entry.is_failed() {
+                let file = entry.path.as_ref().and_then(|p| Path::new(p).file_name())
+                    .map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| entry.name());
+                for finding in &entry.findings {
+                    bar.println(format!("{} {} is {}: {}", "Warning:".yellow(), file, finding.severity, finding));
+                }
             }
 
             bar.inc(1);
             bar.tick();
-            down_size += size;
-            downloaded += 1;
+            if entry.status == DownloadStatus::Downloaded && entry.source.is_none() {
+                report.bytes += entry.bytes;
+            }
+            report.entries.push(entry);
         }
 
         bar.finish();
-        println!("{} downloaded {}", "Successfully".green(), HumanBytes(down_size as u64).to_string().bold());
+        if !self.quiet {
+            println!("{} downloaded {}", "Successfully".green(), HumanBytes(report.bytes as u64).to_string().bold());
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_filename_takes_last_segment() {
+        assert_eq!(url_filename("https://cdn.modrinth.com/data/AANobbMI/versions/4.0.1/sodium-fabric-0.5.3.jar"),
+                   "sodium-fabric-0.5.3.jar");
+        assert_eq!(url_filename("https://edge.forgecdn.net/files/4712/125/jei-1.20.1-forge-15.2.0.27.jar"),
+                   "jei-1.20.1-forge-15.2.0.27.jar");
+        assert_eq!(url_filename("mod.jar"), "mod.jar");
+    }
+
+    #[test]
+    fn url_filename_drops_query() {
+        assert_eq!(url_filename("https://github.com/a/b/releases/download/v1/mod.jar?raw=true"), "mod.jar");
+        assert_eq!(url_filename("https://example.com/dir/"), "");
+    }
+
+    // Entry installed as jar that isn't a zip at all
+    fn unreadable_entry(name: &str) -> (DownloadEntry, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!("mcget-{}-{}.jar", name, std::process::id()));
+        std::fs::write(&path, b"not a zip").unwrap();
+
+        let mut entry = DownloadEntry::new(&DownloadTarget::with_url("https://example.com/mod.jar".to_string(),
+                                                                      String::new()));
+        entry.path = Some(path.to_string_lossy().to_string());
+        (entry, path)
+    }
+
+    #[test]
+    fn unreadable_jar_fails_scan() {
+        let (mut entry, path) = unreadable_entry("warn");
+        assert!(MassDownloader::scan_process(ScanMode::Warn, &mut entry).is_err());
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn unreadable_jar_is_removed_when_blocking() {
+        let (mut entry, path) = unreadable_entry("block");
+        assert!(MassDownloader::scan_process(ScanMode::Block, &mut entry).is_err());
+        assert!(!path.exists());
     }
 }
@@ -0,0 +1,144 @@
+use {
+    sha1::{Sha1, Digest},
+    sha2::{Sha256, Sha512}
+};
+
+pub fn sha1_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha1::digest(data))
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+pub fn sha512_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha512::digest(data))
+}
+
+// Hash in one of packwiz/Modrinth formats, None if format isn't supported
+pub fn hash_hex(format: &str, data: &[u8]) -> Option<String> {
+    match format {
+        "sha1" => Some(sha1_hex(data)),
+        "sha256" => Some(sha256_hex(data)),
+        "sha512" => Some(sha512_hex(data)),
+        _ => None
+    }
+}
+
+// MurmurHash2, 32 bit
+pub fn murmur2(data: &[u8], seed: u32) -> u32 {
+    const M: u32 = 0x5bd1e995;
+
+    let mut h = seed ^ data.len() as u32;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> 24;
+        k = k.wrapping_mul(M);
+
+        h = h.wrapping_mul(M) ^ k;
+    }
+
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        for (i, byte) in rest.iter().enumerate() {
+            h ^= (*byte as u32) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^ (h >> 15)
+}
+
+// CurseForge file fingerprint: murmur2 of file without whitespace bytes
+pub fn curseforge_fingerprint(data: &[u8]) -> u32 {
+    let stripped: Vec<u8> = data.iter().copied().filter(|b| !matches!(b, 9 | 10 | 13 | 32)).collect();
+    murmur2(&stripped, 1)
+}
+
+// What a file is known to be, from CurseForge or the lock file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExpectedFile {
+    pub size: Option<usize>,
+    pub sha1: Option<String>,
+    pub sha512: Option<String>,
+    pub fingerprint: Option<u32>
+}
+
+impl ExpectedFile {
+    pub fn is_known(&self) -> bool {
+        self.sha1.is_some() || self.sha512.is_some() || self.fingerprint.is_some()
+    }
+
+    // Error describes the first mismatch
+    pub fn check(&self, data: &[u8]) -> Result<(), String> {
+        if let Some(size) = self.size.filter(|s| *s != data.len()) {
+            return Err(format!("size is {}, expected {}", data.len(), size));
+        }
+        if let Some(sha1) = self.sha1.as_ref().filter(|h| !h.eq_ignore_ascii_case(&sha1_hex(data))) {
+            return Err(format!("sha1 doesn't match {}", sha1));
+        }
+        if let Some(sha512) = self.sha512.as_ref().filter(|h| !h.eq_ignore_ascii_case(&sha512_hex(data))) {
+            return Err(format!("sha512 doesn't match {}", sha512));
+        }
+        if let Some(fingerprint) = self.fingerprint.filter(|f| *f != curseforge_fingerprint(data)) {
+            return Err(format!("fingerprint doesn't match {}", fingerprint));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn murmur2_matches_reference() {
+        assert_eq!(murmur2(b"", 0), 0);
+        assert_eq!(murmur2(b"a", 0), 0x92685f5e);
+        assert_eq!(murmur2(b"ab", 0), 0x1aa14063);
+        assert_eq!(murmur2(b"abc", 0), 0x13577c9b);
+        assert_eq!(murmur2(b"abcd", 0), 0x26873021);
+        assert_eq!(murmur2(b"The quick brown fox jumps over the lazy dog", 0), 0x212729d0);
+        assert_eq!(murmur2(b"", 1), 0x5bd15e36);
+        assert_eq!(murmur2(b"hello world", 1), 0x83ea5dee);
+    }
+
+    // SMHasher verification: hashes of prefixes of 0..255 with decreasing seeds, hashed together
+    #[test]
+    fn murmur2_passes_smhasher_verification() {
+        let key: Vec<u8> = (0..=255).collect();
+        let mut hashes = vec![];
+        for len in 0..256 {
+            hashes.extend(murmur2(&key[..len], 256 - len as u32).to_le_bytes());
+        }
+
+        assert_eq!(murmur2(&hashes, 0), 0x27864c1e);
+    }
+
+    #[test]
+    fn fingerprint_ignores_whitespace() {
+        assert_eq!(curseforge_fingerprint(b"helloworld"), 0xa85cbded);
+        assert_eq!(curseforge_fingerprint(b"hello world"), 0xa85cbded);
+        assert_eq!(curseforge_fingerprint(b" hello\tworld\r\n"), 0xa85cbded);
+        assert_eq!(curseforge_fingerprint(b""), murmur2(b"", 1));
+    }
+
+    #[test]
+    fn expected_file_reports_mismatches() {
+        let data = b"hello world";
+        let mut expected = ExpectedFile{size: Some(data.len()), ..Default::default()};
+        assert!(!expected.is_known());
+
+        expected.sha1 = Some(sha1_hex(data).to_uppercase());
+        expected.fingerprint = Some(curseforge_fingerprint(data));
+        assert!(expected.is_known());
+        assert_eq!(expected.check(data), Ok(()));
+        assert!(expected.check(b"hello there").unwrap_err().starts_with("sha1"));
+        assert!(expected.check(b"hello").unwrap_err().starts_with("size"));
+    }
+}
@@ -0,0 +1,635 @@
+use {
+    std::{fs::File, io::{Cursor, Read, Seek}, path::Path},
+    serde::Serialize,
+    zip::ZipArchive,
+    crate::{mod_loader::ModLoader, side::Side, version_range::VersionRange}
+};
+
+// Loader metadata file inside mod jar
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MetadataFormat {
+    FabricModJson,
+    QuiltModJson,
+    NeoForgeModsToml,
+    ModsToml,
+
+    // Forge before 1.13
+    McmodInfo
+}
+
+impl MetadataFormat {
+    pub const ALL: [MetadataFormat; 5] = [
+        MetadataFormat::FabricModJson, MetadataFormat::QuiltModJson,
+        MetadataFormat::NeoForgeModsToml, MetadataFormat::ModsToml, MetadataFormat::McmodInfo
+    ];
+
+    pub fn file(&self) -> &'static str {
+        match self {
+            MetadataFormat::FabricModJson => "fabric.mod.json",
+            MetadataFormat::QuiltModJson => "quilt.mod.json",
+            MetadataFormat::NeoForgeModsToml => "META-INF/neoforge.mods.toml",
+            MetadataFormat::ModsToml => "META-INF/mods.toml",
+            MetadataFormat::McmodInfo => "mcmod.info"
+        }
+    }
+
+    pub fn loader(&self) -> ModLoader {
+        match self {
+            MetadataFormat::FabricModJson => ModLoader::Fabric,
+            MetadataFormat::QuiltModJson => ModLoader::Quilt,
+            MetadataFormat::NeoForgeModsToml => ModLoader::NeoForge,
+            MetadataFormat::ModsToml | MetadataFormat::McmodInfo => ModLoader::Forge
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyKind {
+    Required,
+    Optional,
+    Incompatible
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct JarDependency {
+    pub id: String,
+
+    // Version requirement as written in metadata, `*` if there is none
+    pub versions: String,
+    pub kind: DependencyKind,
+
+    // Where dependency is needed, only mods.toml says it
+    pub side: Side,
+
+    // None if requirement can't be parsed
+    #[serde(skip)]
+    pub range: Option<VersionRange>
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct JarMod {
+    pub id: String,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub dependencies: Vec<JarDependency>,
+
+    // Other ids the mod can be required by
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub provides: Vec<String>
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ModMetadata {
+    pub format: MetadataFormat,
+    pub mods: Vec<JarMod>,
+    pub side: Option<Side>
+}
+
+impl ModMetadata {
+    pub fn loader(&self) -> ModLoader {
+        self.format.loader()
+    }
+
+    pub fn dependencies(&self) -> impl Iterator<Item = &JarDependency> {
+        self.mods.iter().flat_map(|m| &m.dependencies)
+    }
+
+    // Ranges of required dependency `id` declared by mods of the jar
+    pub fn requirements(&self, id: &str) -> Vec<VersionRange> {
+        self.dependencies()
+            .filter(|d| d.kind == DependencyKind::Required && d.id == id)
+            .filter_map(|d| d.range.clone())
+            .collect()
+    }
+
+    // Supported minecraft versions
+    pub fn minecraft(&self) -> Option<VersionRange> {
+        self.requirements("minecraft").into_iter().next()
+    }
+
+    // Required versions of mod loader the metadata is made for
+    pub fn loader_requirements(&self, loader: &ModLoader) -> Vec<VersionRange> {
+        match loader_mod_id(loader) {
+            Some(id) => self.requirements(id),
+            None => vec![]
+        }
+    }
+}
+
+// Mod id under which loader itself can be required
+pub fn loader_mod_id(loader: &ModLoader) -> Option<&'static str> {
+    match loader {
+        ModLoader::Fabric => Some("fabricloader"),
+        ModLoader::Quilt => Some("quilt_loader"),
+        ModLoader::Forge => Some("forge"),
+        ModLoader::NeoForge => Some("neoforge"),
+        _ => None
+    }
+}
+
+// Metadata of all loaders found in jar
+#[derive(Serialize, Clone, Debug)]
+pub struct JarMeta {
+    pub metadata: Vec<ModMetadata>,
+
+    // Jars bundled inside of META-INF (jar-in-jar)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub nested: Vec<JarMeta>
+}
+
+impl JarMeta {
+    // None if file isn't a zip archive
+    pub fn read(path: &Path) -> Option<JarMeta> {
+        Self::read_archive(ZipArchive::new(File::open(path).ok()?).ok()?)
+    }
+
+    fn read_archive<R: Read + Seek>(mut archive: ZipArchive<R>) -> Option<JarMeta> {
+        let manifest_version = read_jar_entry(&mut archive, "META-INF/MANIFEST.MF")
+            .and_then(|manifest| manifest.lines()
+                .find_map(|l| l.strip_prefix("Implementation-Version:").map(|v| v.trim().to_string())));
+
+        let mut metadata = vec![];
+        for format in MetadataFormat::ALL {
+            let contents = match read_jar_entry(&mut archive, format.file()) {
+                Some(contents) => contents,
+                None => { continue; }
+            };
+
+            let parsed = match format {
+                MetadataFormat::FabricModJson => parse_fabric(&contents),
+                MetadataFormat::QuiltModJson => parse_quilt(&contents),
+                MetadataFormat::NeoForgeModsToml | MetadataFormat::ModsToml =>
+                    parse_mods_toml(format, &contents, manifest_version.as_deref()),
+                MetadataFormat::McmodInfo => parse_mcmod_info(&contents)
+            };
+            metadata.extend(parsed);
+        }
+
+        let bundled: Vec<String> = archive.file_names()
+            .filter(|name| name.starts_with("META-INF/") && name.ends_with(".jar"))
+            .map(|name| name.to_string())
+            .collect();
+        let mut nested = vec![];
+        for name in bundled {
+            let mut bytes = vec![];
+            let read = archive.by_name(&name).map(|mut entry| entry.read_to_end(&mut bytes));
+            if !matches!(read, Ok(Ok(_))) {
+                continue;
+            }
+
+            if let Some(jar) = ZipArchive::new(Cursor::new(bytes)).ok().and_then(Self::read_archive) {
+                nested.push(jar);
+            }
+        }
+
+        Some(JarMeta{metadata, nested})
+    }
+
+    // Metadata used by `loader`, old NeoForge versions read Forge `mods.toml`
+    pub fn for_loader(&self, loader: &ModLoader) -> Option<&ModMetadata> {
+        self.metadata.iter().find(|m| m.loader() == *loader)
+            .or_else(|| self.metadata.iter().find(|m| loader.can_load(&m.loader())))
+            .or_else(|| match loader {
+                ModLoader::NeoForge => self.metadata.iter().find(|m| m.format == MetadataFormat::ModsToml),
+                _ => None
+            })
+    }
+
+    pub fn mods(&self) -> impl Iterator<Item = &JarMod> {
+        self.metadata.iter().flat_map(|m| &m.mods)
+    }
+
+    // Mods of `loader` including bundled ones
+    pub fn all_mods(&self, loader: &ModLoader) -> Vec<&JarMod> {
+        let mut mods: Vec<&JarMod> = self.for_loader(loader).map(|m| m.mods.iter().collect()).unwrap_or_default();
+        for jar in &self.nested {
+            mods.extend(jar.all_mods(loader));
+        }
+
+        mods
+    }
+
+    pub fn mod_ids(&self) -> Vec<&str> {
+        let mut ids: Vec<&str> = self.mods().map(|m| m.id.as_str()).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    // Side of the first metadata file, Fabric and Quilt ones take precedence
+    pub fn side(&self) -> Option<Side> {
+        self.metadata.first()?.side
+    }
+}
+
+pub fn read_jar_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Option<String> {
+    let mut entry = archive.by_name(name).ok()?;
+    let mut contents = String::new();
+    entry.read_to_string(&mut contents).ok()?;
+
+    Some(contents)
+}
+
+fn environment_side(environment: &str) -> Option<Side> {
+    match environment {
+        "client" => Some(Side::Client),
+        "server" | "dedicated_server" => Some(Side::Server),
+        _ => None
+    }
+}
+
+fn json_str(value: &serde_json::Value, key: &str) -> Option<String> {
+    value.get(key).and_then(|v| v.as_str()).map(|v| v.to_string())
+}
+
+// Fabric and Quilt requirement: string or list of alternatives
+fn json_dependency(id: &str, versions: Option<&serde_json::Value>, kind: DependencyKind) -> JarDependency {
+    let alternatives: Vec<String> = match versions {
+        Some(serde_json::Value::String(range)) => vec![range.clone()],
+        Some(serde_json::Value::Array(list)) => list.iter().filter_map(|v| v.as_str().map(|v| v.to_string())).collect(),
+        _ => vec!["*".to_string()]
+    };
+
+    let parsed: Option<Vec<VersionRange>> = alternatives.iter().map(|a| VersionRange::parse(a)).collect();
+    let range = parsed.map(|ranges| VersionRange{
+        alternatives: ranges.into_iter().flat_map(|r| r.alternatives).collect()
+    });
+
+    JarDependency{id: id.to_string(), versions: alternatives.join(" || "), kind, side: Side::Both, range}
+}
+
+// Forge treats plain version as minimal one
+fn forge_dependency(id: &str, versions: Option<&str>, kind: DependencyKind) -> JarDependency {
+    let versions = versions.filter(|v| !v.is_empty()).unwrap_or("*");
+    let range = if versions.starts_with('[') || versions.starts_with('(') || versions == "*" {
+        VersionRange::parse(versions)
+    } else {
+        VersionRange::parse(&format!(">={}", versions))
+    };
+
+    JarDependency{id: id.to_string(), versions: versions.to_string(), kind, side: Side::Both, range}
+}
+
+fn parse_fabric(contents: &str) -> Option<ModMetadata> {
+    let meta: serde_json::Value = serde_json::from_str(contents).ok()?;
+
+    let mut dependencies = vec![];
+    for (key, kind) in [("depends", DependencyKind::Required), ("recommends", DependencyKind::Optional),
+                        ("suggests", DependencyKind::Optional), ("breaks", DependencyKind::Incompatible)] {
+        for (id, versions) in meta.get(key).and_then(|d| d.as_object()).into_iter().flatten() {
+            dependencies.push(json_dependency(id, Some(versions), kind));
+        }
+    }
+
+    Some(ModMetadata{
+        format: MetadataFormat::FabricModJson,
+        mods: vec![JarMod{
+            id: json_str(&meta, "id")?,
+            name: json_str(&meta, "name"),
+            version: json_str(&meta, "version"),
+            dependencies,
+            provides: meta.get("provides").and_then(|p| p.as_array()).into_iter().flatten()
+                .filter_map(|p| p.as_str().map(|p| p.to_string()))
+                .collect()
+        }],
+        side: meta.get("environment").and_then(|e| e.as_str()).and_then(environment_side)
+    })
+}
+
+fn parse_quilt(contents: &str) -> Option<ModMetadata> {
+    let meta: serde_json::Value = serde_json::from_str(contents).ok()?;
+    let loader = meta.get("quilt_loader")?;
+
+    let mut dependencies = vec![];
+    for (key, kind) in [("depends", DependencyKind::Required), ("breaks", DependencyKind::Incompatible)] {
+        for dep in loader.get(key).and_then(|d| d.as_array()).into_iter().flatten() {
+            match dep {
+                serde_json::Value::String(id) => dependencies.push(json_dependency(id, None, kind)),
+                serde_json::Value::Object(_) => {
+                    let id = match dep.get("id").and_then(|id| id.as_str()) {
+                        Some(id) => id,
+                        None => { continue; }
+                    };
+                    let optional = dep.get("optional").and_then(|o| o.as_bool()).unwrap_or(false);
+                    let kind = if optional && kind == DependencyKind::Required { DependencyKind::Optional } else { kind };
+                    dependencies.push(json_dependency(id, dep.get("versions"), kind));
+                },
+                _ => {}
+            }
+        }
+    }
+
+    Some(ModMetadata{
+        format: MetadataFormat::QuiltModJson,
+        mods: vec![JarMod{
+            id: json_str(loader, "id")?,
+            name: loader.get("metadata").and_then(|m| json_str(m, "name")),
+            version: json_str(loader, "version"),
+            dependencies,
+            // `id` or {"id": .., "version": ..}
+            provides: loader.get("provides").and_then(|p| p.as_array()).into_iter().flatten()
+                .filter_map(|p| p.as_str().map(|p| p.to_string()).or_else(|| json_str(p, "id")))
+                .collect()
+        }],
+        side: meta.pointer("/minecraft/environment").and_then(|e| e.as_str()).and_then(environment_side)
+    })
+}
+
+fn parse_mods_toml(format: MetadataFormat, contents: &str, manifest_version: Option<&str>) -> Option<ModMetadata> {
+    let meta: toml::Value = contents.parse().ok()?;
+    let dependencies = meta.get("dependencies").and_then(|d| d.as_table());
+
+    let mut mods = vec![];
+    for mod_ in meta.get("mods").and_then(|m| m.as_array()).into_iter().flatten() {
+        let id = match mod_.get("modId").and_then(|id| id.as_str()) {
+            Some(id) => id.to_string(),
+            None => { continue; }
+        };
+
+        // Version is usually taken from jar manifest
+        let version = mod_.get("version").and_then(|v| v.as_str()).map(|v| match v {
+            "${file.jarVersion}" => manifest_version.unwrap_or(v).to_string(),
+            _ => v.to_string()
+        });
+
+        let mut deps = vec![];
+        for dep in dependencies.and_then(|d| d.get(&id)).and_then(|d| d.as_array()).into_iter().flatten() {
+            let dep_id = match dep.get("modId").and_then(|id| id.as_str()) {
+                Some(id) => id,
+                None => { continue; }
+            };
+
+            // Older files have `mandatory`, newer ones `type`
+            let kind = match (dep.get("type").and_then(|t| t.as_str()), dep.get("mandatory").and_then(|m| m.as_bool())) {
+                (Some(t), _) if t.eq_ignore_ascii_case("required") => DependencyKind::Required,
+                (Some(t), _) if t.eq_ignore_ascii_case("incompatible") => DependencyKind::Incompatible,
+                (Some(_), _) => DependencyKind::Optional,
+                (None, Some(false)) => DependencyKind::Optional,
+                (None, _) => DependencyKind::Required
+            };
+
+            let mut dependency = forge_dependency(dep_id, dep.get("versionRange").and_then(|r| r.as_str()), kind);
+            dependency.side = match dep.get("side").and_then(|s| s.as_str()) {
+                Some(side) if side.eq_ignore_ascii_case("client") => Side::Client,
+                Some(side) if side.eq_ignore_ascii_case("server") => Side::Server,
+                _ => Side::Both
+            };
+            deps.push(dependency);
+        }
+
+        mods.push(JarMod{
+            id,
+            name: mod_.get("displayName").and_then(|n| n.as_str()).map(|n| n.to_string()),
+            version,
+            dependencies: deps,
+            provides: vec![]
+        });
+    }
+
+    // `displayTest` only tells whether server checks client version, it says nothing about side
+    let side = match meta.get("clientSideOnly").and_then(|v| v.as_bool()) {
+        Some(true) => Some(Side::Client),
+        _ => None
+    };
+
+    Some(ModMetadata{format, mods, side})
+}
+
+fn parse_mcmod_info(contents: &str) -> Option<ModMetadata> {
+    let meta: serde_json::Value = serde_json::from_str(contents).ok()?;
+    let list = match &meta {
+        serde_json::Value::Array(list) => list.clone(),
+        _ => meta.get("modList").and_then(|l| l.as_array()).cloned()?
+    };
+
+    let mut mods = vec![];
+    for mod_ in &list {
+        let id = match json_str(mod_, "modid") {
+            Some(id) => id,
+            None => { continue; }
+        };
+
+        let mut dependencies = vec![];
+        if let Some(mc) = json_str(mod_, "mcversion") {
+            let range = VersionRange::parse(&mc);
+            dependencies.push(JarDependency{id: "minecraft".to_string(), versions: mc, kind: DependencyKind::Required,
+                                            side: Side::Both, range});
+        }
+
+        // `modid` or `modid@[range]`
+        for dep in mod_.get("requiredMods").and_then(|r| r.as_array()).into_iter().flatten().filter_map(|d| d.as_str()) {
+            let (dep_id, versions) = match dep.split_once('@') {
+                Some((dep_id, versions)) => (dep_id, Some(versions)),
+                None => (dep, None)
+            };
+            dependencies.push(forge_dependency(dep_id, versions, DependencyKind::Required));
+        }
+
+        mods.push(JarMod{id, name: json_str(mod_, "name"), version: json_str(mod_, "version"),
+                         dependencies, provides: vec![]});
+    }
+
+    Some(ModMetadata{format: MetadataFormat::McmodInfo, mods, side: None})
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::io::Write,
+        zip::{ZipWriter, write::FileOptions}
+    };
+
+    fn jar(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+        for (name, contents) in entries {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn read(entries: &[(&str, &[u8])]) -> JarMeta {
+        JarMeta::read_archive(ZipArchive::new(Cursor::new(jar(entries))).unwrap()).unwrap()
+    }
+
+    const FABRIC: &str = r#"{
+        "schemaVersion": 1,
+        "id": "sodium",
+        "name": "Sodium",
+        "version": "0.5.3",
+        "environment": "client",
+        "provides": ["rubidium"],
+        "depends": {"fabricloader": ">=0.12.0", "minecraft": ["1.20", "1.20.1"]},
+        "breaks": {"optifabric": "*"}
+    }"#;
+
+    #[test]
+    fn reads_fabric_mod_json() {
+        let meta = read(&[("fabric.mod.json", FABRIC.as_bytes())]);
+        let fabric = meta.for_loader(&ModLoader::Fabric).unwrap();
+        let mod_ = &fabric.mods[0];
+
+        assert_eq!(fabric.format, MetadataFormat::FabricModJson);
+        assert_eq!(mod_.id, "sodium");
+        assert_eq!(mod_.name.as_deref(), Some("Sodium"));
+        assert_eq!(mod_.version.as_deref(), Some("0.5.3"));
+        assert_eq!(mod_.provides, vec!["rubidium"]);
+        assert_eq!(meta.side(), Some(Side::Client));
+
+        assert!(fabric.minecraft().unwrap().contains("1.20.1"));
+        assert!(!fabric.minecraft().unwrap().contains("1.20.2"));
+        assert!(fabric.loader_requirements(&ModLoader::Fabric)[0].contains("0.14.21"));
+        assert!(fabric.dependencies().any(|d| d.id == "optifabric" && d.kind == DependencyKind::Incompatible));
+    }
+
+    #[test]
+    fn reads_quilt_mod_json() {
+        let quilt = r#"{
+            "schema_version": 1,
+            "quilt_loader": {
+                "id": "qsl",
+                "version": "6.1.0",
+                "metadata": {"name": "Quilt Standard Libraries"},
+                "provides": [{"id": "quilted_fabric_api", "version": "7.0.0"}],
+                "depends": [
+                    "quilt_loader",
+                    {"id": "minecraft", "versions": ">=1.20"},
+                    {"id": "modmenu", "optional": true}
+                ]
+            },
+            "minecraft": {"environment": "dedicated_server"}
+        }"#;
+        let meta = read(&[("quilt.mod.json", quilt.as_bytes())]);
+        let quilt = meta.for_loader(&ModLoader::Quilt).unwrap();
+        let mod_ = &quilt.mods[0];
+
+        assert_eq!(mod_.id, "qsl");
+        assert_eq!(mod_.name.as_deref(), Some("Quilt Standard Libraries"));
+        assert_eq!(mod_.provides, vec!["quilted_fabric_api"]);
+        assert_eq!(meta.side(), Some(Side::Server));
+        assert!(quilt.minecraft().unwrap().contains("1.20.1"));
+        assert!(quilt.dependencies().any(|d| d.id == "modmenu" && d.kind == DependencyKind::Optional));
+    }
+
+    #[test]
+    fn reads_mods_toml_with_manifest_version() {
+        let toml = r#"
+            modLoader = "javafml"
+            loaderVersion = "[47,)"
+
+            [[mods]]
+            modId = "create"
+            version = "${file.jarVersion}"
+            displayName = "Create"
+            displayTest = "IGNORE_SERVER_VERSION"
+
+            [[dependencies.create]]
+            modId = "forge"
+            mandatory = true
+            versionRange = "[47.1.3,)"
+
+            [[dependencies.create]]
+            modId = "minecraft"
+            type = "required"
+            versionRange = "[1.20.1,1.21)"
+
+            [[dependencies.create]]
+            modId = "jei"
+            type = "optional"
+            versionRange = "15.2"
+
+            [[dependencies.create]]
+            modId = "flywheel"
+            mandatory = true
+            versionRange = "[0.6.10,0.6.11)"
+            side = "CLIENT"
+        "#;
+        let manifest = "Manifest-Version: 1.0\nImplementation-Version: 0.5.1.f\n";
+        let meta = read(&[("META-INF/MANIFEST.MF", manifest.as_bytes()), ("META-INF/mods.toml", toml.as_bytes())]);
+        let forge = meta.for_loader(&ModLoader::Forge).unwrap();
+        let mod_ = &forge.mods[0];
+
+        assert_eq!(forge.format, MetadataFormat::ModsToml);
+        assert_eq!(mod_.id, "create");
+        assert_eq!(mod_.name.as_deref(), Some("Create"));
+        assert_eq!(mod_.version.as_deref(), Some("0.5.1.f"));
+        assert_eq!(meta.side(), None);
+
+        assert!(forge.loader_requirements(&ModLoader::Forge)[0].contains("47.2.0"));
+        assert!(!forge.loader_requirements(&ModLoader::Forge)[0].contains("47.1.0"));
+        assert!(!forge.minecraft().unwrap().contains("1.21"));
+
+        // Plain Forge version is the minimal one
+        let jei = forge.dependencies().find(|d| d.id == "jei").unwrap();
+        assert_eq!(jei.kind, DependencyKind::Optional);
+        assert!(jei.range.as_ref().unwrap().contains("15.3"));
+
+        let flywheel = forge.dependencies().find(|d| d.id == "flywheel").unwrap();
+        assert_eq!(flywheel.side, Side::Client);
+        assert_eq!(jei.side, Side::Both);
+
+        // NeoForge reads old mods.toml too
+        assert!(meta.for_loader(&ModLoader::NeoForge).is_some());
+    }
+
+    #[test]
+    fn reads_client_side_only_mods_toml() {
+        let toml = "clientSideOnly = true\n[[mods]]\nmodId = \"oculus\"\nversion = \"1.6.9\"\n";
+        let meta = read(&[("META-INF/mods.toml", toml.as_bytes())]);
+
+        assert_eq!(meta.side(), Some(Side::Client));
+        assert_eq!(meta.mods().next().unwrap().version.as_deref(), Some("1.6.9"));
+    }
+
+    #[test]
+    fn reads_mcmod_info() {
+        let info = r#"[{
+            "modid": "jei",
+            "name": "Just Enough Items",
+            "version": "4.16.1",
+            "mcversion": "1.12.2",
+            "requiredMods": ["forge@[14.23.5.2816,)", "baubles"]
+        }]"#;
+        let meta = read(&[("mcmod.info", info.as_bytes())]);
+        let forge = meta.for_loader(&ModLoader::Forge).unwrap();
+
+        assert_eq!(forge.format, MetadataFormat::McmodInfo);
+        assert_eq!(forge.mods[0].name.as_deref(), Some("Just Enough Items"));
+        assert!(forge.minecraft().unwrap().contains("1.12.2"));
+        assert!(forge.loader_requirements(&ModLoader::Forge)[0].contains("14.23.5.2860"));
+        assert!(forge.requirements("baubles")[0].contains("1.5.2"));
+    }
+
+    #[test]
+    fn reads_nested_jars() {
+        let inner = jar(&[("fabric.mod.json", br#"{"id": "fabric-api-base", "version": "0.4.31"}"#)]);
+        let meta = read(&[
+            ("fabric.mod.json", FABRIC.as_bytes()),
+            ("META-INF/jars/fabric-api-base-0.4.31.jar", &inner),
+            ("META-INF/jars/broken.jar", b"not a zip")
+        ]);
+
+        assert_eq!(meta.nested.len(), 1);
+        let ids: Vec<&str> = meta.all_mods(&ModLoader::Fabric).iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["sodium", "fabric-api-base"]);
+        assert!(meta.all_mods(&ModLoader::Forge).is_empty());
+    }
+
+    #[test]
+    fn deduplicates_mod_ids() {
+        let toml = "[[mods]]\nmodId = \"sodium\"\n[[mods]]\nmodId = \"indium\"\n[[mods]]\nmodId = \"sodium\"\n";
+        let meta = read(&[("fabric.mod.json", FABRIC.as_bytes()), ("META-INF/mods.toml", toml.as_bytes())]);
+
+        assert_eq!(meta.mod_ids(), vec!["indium", "sodium"]);
+    }
+
+    #[test]
+    fn rejects_files_which_are_not_zips() {
+        assert!(ZipArchive::new(Cursor::new(b"plain text".to_vec())).is_err());
+        assert!(JarMeta::read(Path::new("/nonexistent/mod.jar")).is_none());
+    }
+}
@@ -1,6 +1,15 @@
 pub mod api;
 pub mod objects;
+pub mod mod_loader;
 pub mod downloader;
 pub mod dependency_resolver;
+pub mod hash;
+pub mod loader;
+pub mod version_range;
+pub mod mc_version;
+pub mod side;
+pub mod jar;
+pub mod modrinth;
+pub mod scanner;
 
 pub mod prelude;
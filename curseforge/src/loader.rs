@@ -0,0 +1,458 @@
+use {
+    std::{
+        io::{Cursor, Read},
+        path::Path
+    },
+    serde::{Serialize, Deserialize},
+    serde_json::Value,
+    hyper::{Client, client::HttpConnector},
+    hyper_tls::HttpsConnector,
+    zip::ZipArchive,
+
+    crate::{api::*, objects::GameVersion, downloader::*, mod_loader::ModLoader, mc_version::*}
+};
+
+const MINECRAFT_LIBRARIES: &str = "https://libraries.minecraft.net/";
+
+// Where loader metadata, installers and libraries are fetched from
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LoaderUrls {
+    #[serde(rename = "FabricMeta", default = "default_fabric_meta")]
+    pub fabric_meta: String,
+
+    #[serde(rename = "QuiltMeta", default = "default_quilt_meta")]
+    pub quilt_meta: String,
+
+    #[serde(rename = "ForgeMaven", default = "default_forge_maven")]
+    pub forge_maven: String,
+
+    #[serde(rename = "NeoForgeMaven", default = "default_neoforge_maven")]
+    pub neoforge_maven: String,
+
+    // Mojang version list, range packs use the newest release it has in range
+    #[serde(rename = "VersionManifest", default = "default_version_manifest")]
+    pub version_manifest: String,
+
+    // Mirror with maven layout used for every library instead of its own URL
+    #[serde(rename = "Libraries", default, skip_serializing_if = "Option::is_none")]
+    pub libraries: Option<String>
+}
+
+fn default_fabric_meta() -> String {
+    "https://meta.fabricmc.net/v2".to_string()
+}
+
+fn default_quilt_meta() -> String {
+    "https://meta.quiltmc.org/v3".to_string()
+}
+
+fn default_forge_maven() -> String {
+    "https://maven.minecraftforge.net".to_string()
+}
+
+fn default_neoforge_maven() -> String {
+    "https://maven.neoforged.net/releases".to_string()
+}
+
+fn default_version_manifest() -> String {
+    "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json".to_string()
+}
+
+impl Default for LoaderUrls {
+    fn default() -> Self {
+        LoaderUrls{
+            fabric_meta: default_fabric_meta(),
+            quilt_meta: default_quilt_meta(),
+            forge_maven: default_forge_maven(),
+            neoforge_maven: default_neoforge_maven(),
+            version_manifest: default_version_manifest(),
+            libraries: None
+        }
+    }
+}
+
+impl LoaderUrls {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct LoaderLibrary {
+    pub name: String,
+
+    // Path inside of `libraries` directory
+    pub path: String,
+
+    // None for libraries shipped inside of the installer
+    pub url: Option<String>,
+    pub sha1: Option<String>
+}
+
+// Version JSON of mod loader with everything needed to install it
+pub struct LoaderProfile {
+    pub id: String,
+    pub json: Value,
+    pub libraries: Vec<LoaderLibrary>,
+
+    pub installer: Option<Vec<u8>>,
+
+    // Installer entries copied into `libraries` as (entry name, library path)
+    pub embedded: Vec<(String, String)>,
+
+    // Installer steps patching the game jar, McGet can't run them and refuses to install such profiles
+    pub processors: usize
+}
+
+#[derive(Serialize)]
+pub struct LoaderInstallReport {
+    pub id: String,
+    pub version_json: String,
+    pub libraries: usize,
+    pub extracted: usize,
+    pub download: DownloadReport
+}
+
+// `group:artifact:version[:classifier][@extension]` as maven repository path
+pub fn maven_path(name: &str) -> Option<String> {
+    let (coords, extension) = match name.split_once('@') {
+        Some((coords, extension)) => (coords, extension),
+        None => (name, "jar")
+    };
+
+    let parts: Vec<&str> = coords.split(':').collect();
+    if parts.len() < 3 {
+        return None;
+    }
+
+    let (group, artifact, version) = (parts[0].replace('.', "/"), parts[1], parts[2]);
+    let filename = match parts.get(3) {
+        Some(classifier) => format!("{}-{}-{}.{}", artifact, version, classifier, extension),
+        None => format!("{}-{}.{}", artifact, version, extension)
+    };
+
+    Some(format!("{}/{}/{}/{}", group, artifact, version, filename))
+}
+
+fn join_url(base: &str, path: &str) -> String {
+    format!("{}/{}", base.trim_end_matches('/'), path.trim_start_matches('/'))
+}
+
+// Library of version JSON, both `downloads.artifact` and `name` + `url` forms
+fn parse_library(value: &Value) -> Option<LoaderLibrary> {
+    let name = value.get("name")?.as_str()?.to_string();
+
+    if let Some(artifact) = value.pointer("/downloads/artifact") {
+        let path = artifact.get("path").and_then(|p| p.as_str()).map(|p| p.to_string())
+            .or_else(|| maven_path(&name))?;
+        let url = artifact.get("url").and_then(|u| u.as_str()).filter(|u| !u.is_empty());
+
+        return Some(LoaderLibrary{
+            name, path, url: url.map(|u| u.to_string()),
+            sha1: artifact.get("sha1").and_then(|s| s.as_str()).map(|s| s.to_string())
+        });
+    }
+
+    // Libraries with natives only have no artifact
+    if value.get("downloads").is_some() {
+        return None;
+    }
+
+    let path = maven_path(&name)?;
+    let base = value.get("url").and_then(|u| u.as_str()).unwrap_or(MINECRAFT_LIBRARIES);
+    Some(LoaderLibrary{
+        url: Some(join_url(base, &path)),
+        sha1: value.get("sha1").and_then(|s| s.as_str()).map(|s| s.to_string()),
+        name, path
+    })
+}
+
+fn parse_libraries(value: Option<&Value>) -> Vec<LoaderLibrary> {
+    value.and_then(|l| l.as_array())
+        .map(|l| l.iter().filter_map(parse_library).collect())
+        .unwrap_or_default()
+}
+
+fn read_zip_entry(archive: &mut ZipArchive<Cursor<&[u8]>>, name: &str) -> Option<Vec<u8>> {
+    let mut entry = archive.by_name(name).ok()?;
+    let mut contents = vec![];
+    entry.read_to_end(&mut contents).ok()?;
+
+    Some(contents)
+}
+
+// Versions listed in maven-metadata.xml
+fn maven_versions(metadata: &str) -> Vec<String> {
+    metadata.split("<version>").skip(1)
+        .filter_map(|v| v.split_once("</version>").map(|(v, _)| v.trim().to_string()))
+        .collect()
+}
+
+// NeoForge versions are named after minecraft version without leading `1.`, e.g. 20.4.x for 1.20.4
+fn neoforge_prefix(version: &str) -> String {
+    let mut parts = version.trim_start_matches("1.").split('.');
+    let major = parts.next().unwrap_or_default();
+    let minor = parts.next().unwrap_or("0");
+
+    format!("{}.{}.", major, minor)
+}
+
+pub struct LoaderInstaller {
+    client: Client<HttpsConnector<HttpConnector>>,
+    urls: LoaderUrls
+}
+
+impl LoaderInstaller {
+    pub fn new(urls: LoaderUrls) -> LoaderInstaller {
+        let https = HttpsConnector::new();
+        let client = Client::builder().build::<_, hyper::Body>(https);
+
+        LoaderInstaller{client, urls}
+    }
+
+    async fn fetch(&self, url: &str) -> RResult<Vec<u8>> {
+        web_get(&self.client, url).await
+    }
+
+    async fn fetch_json(&self, url: &str) -> RResult<Value> {
+        Ok( serde_json::from_slice(&self.fetch(url).await?)? )
+    }
+
+    fn meta_url(&self, loader: &ModLoader) -> Option<&String> {
+        match loader {
+            ModLoader::Fabric => Some(&self.urls.fabric_meta),
+            ModLoader::Quilt => Some(&self.urls.quilt_meta),
+            _ => None
+        }
+    }
+
+    // `version` itself if it's exact, otherwise the newest release from version manifest it allows
+    pub async fn game_version(&self, version: &str) -> RResult<String> {
+        if let Some(exact) = McVersion::parse(version) {
+            return Ok(exact.to_string());
+        }
+
+        let req = McVersionReq::parse(version);
+        let manifest = self.fetch_json(&self.urls.version_manifest).await?;
+        manifest.get("versions").and_then(|v| v.as_array()).into_iter().flatten()
+            .filter(|v| v.get("type").and_then(|t| t.as_str()) == Some("release"))
+            .filter_map(|v| McVersion::parse(v.get("id")?.as_str()?))
+            .filter(|v| req.contains(v))
+            .max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|v| v.to_string())
+            .ok_or_else(|| format!("no minecraft release matches {}", version).into())
+    }
+
+    // Newest loader version for minecraft `version`, stable one for Fabric and Quilt
+    pub async fn latest_version(&self, version: &str, loader: &ModLoader) -> RResult<String> {
+        if let Some(meta) = self.meta_url(loader) {
+            let list = self.fetch_json(&join_url(meta, &format!("versions/loader/{}", version))).await?;
+            let entries = list.as_array().cloned().unwrap_or_default();
+
+            return entries.iter()
+                .filter(|e| e.pointer("/loader/stable").and_then(|s| s.as_bool()) != Some(false))
+                .chain(entries.iter())
+                .find_map(|e| e.pointer("/loader/version").and_then(|v| v.as_str()))
+                .map(|v| v.to_string())
+                .ok_or_else(|| format!("no {} loader for {}", loader, version).into());
+        }
+
+        let (metadata, prefix) = match loader {
+            ModLoader::Forge => (join_url(&self.urls.forge_maven, "net/minecraftforge/forge/maven-metadata.xml"),
+                        format!("{}-", version)),
+            ModLoader::NeoForge => (join_url(&self.urls.neoforge_maven, "net/neoforged/neoforge/maven-metadata.xml"),
+                           neoforge_prefix(version)),
+            _ => { return Err(format!("mod loader {} can't be installed", loader).into()); }
+        };
+
+        let metadata = String::from_utf8(self.fetch(&metadata).await?)?;
+        maven_versions(&metadata).into_iter().rev()
+            .find(|v| v.starts_with(&prefix))
+            .map(|v| v.trim_start_matches(&format!("{}-", version)).to_string())
+            .ok_or_else(|| format!("no {} loader for {}", loader, version).into())
+    }
+
+    // Loader profile from Fabric/Quilt meta or from Forge/NeoForge installer,
+    // `installer` is used instead of downloading one from maven
+    pub async fn profile(&self, version: &str, loader: &ModLoader, loader_version: &str,
+                         installer: Option<&Path>) -> RResult<LoaderProfile> {
+        if let Some(meta) = self.meta_url(loader) {
+            let url = join_url(meta, &format!("versions/loader/{}/{}/profile/json", version, loader_version));
+            let json = self.fetch_json(&url).await?;
+            let id = json.get("id").and_then(|i| i.as_str()).ok_or("loader profile has no id")?.to_string();
+
+            return Ok(LoaderProfile{
+                libraries: parse_libraries(json.get("libraries")),
+                id, json, installer: None, embedded: vec![], processors: 0
+            });
+        }
+
+        let installer = match (installer, loader) {
+            (Some(path), _) => std::fs::read(path)?,
+            (None, ModLoader::Forge) => {
+                let full = format!("{}-{}", version, loader_version);
+                self.fetch(&join_url(&self.urls.forge_maven, &format!(
+                    "net/minecraftforge/forge/{0}/forge-{0}-installer.jar", full
+                ))).await?
+            },
+            (None, ModLoader::NeoForge) => {
+                self.fetch(&join_url(&self.urls.neoforge_maven, &format!(
+                    "net/neoforged/neoforge/{0}/neoforge-{0}-installer.jar", loader_version
+                ))).await?
+            },
+            _ => { return Err(format!("mod loader {} can't be installed", loader).into()); }
+        };
+
+        Self::installer_profile(installer)
+    }
+
+    fn installer_profile(installer: Vec<u8>) -> RResult<LoaderProfile> {
+        let mut archive = ZipArchive::new(Cursor::new(installer.as_slice()))?;
+        let install: Value = serde_json::from_slice(
+            &read_zip_entry(&mut archive, "install_profile.json").ok_or("installer has no install_profile.json")?
+        )?;
+
+        let mut embedded = vec![];
+        for idx in 0..archive.len() {
+            let entry = archive.by_index(idx)?;
+            if let Some(path) = entry.name().strip_prefix("maven/").filter(|_| !entry.is_dir()) {
+                embedded.push((entry.name().to_string(), path.to_string()));
+            }
+        }
+
+        // Installers before 1.12.2-14.23.5.2851 keep version JSON inside of install profile
+        let (json, mut libraries, processors) = if let Some(info) = install.get("versionInfo") {
+            let mut libraries = parse_libraries(info.get("libraries"));
+            let universal = install.pointer("/install/path").and_then(|p| p.as_str());
+            let file = install.pointer("/install/filePath").and_then(|p| p.as_str());
+            if let (Some(name), Some(file)) = (universal, file) {
+                let path = maven_path(name).ok_or("invalid universal jar name")?;
+                libraries.retain(|l| l.name != name);
+                libraries.push(LoaderLibrary{name: name.to_string(), path: path.clone(), url: None, sha1: None});
+                embedded.push((file.to_string(), path));
+            }
+
+            (info.clone(), libraries, 0)
+        } else {
+            let json: Value = serde_json::from_slice(
+                &read_zip_entry(&mut archive, "version.json").ok_or("installer has no version.json")?
+            )?;
+            let mut libraries = parse_libraries(json.get("libraries"));
+            libraries.extend(parse_libraries(install.get("libraries")));
+            let processors = install.get("processors").and_then(|p| p.as_array())
+                .map(|p| p.len()).unwrap_or(0);
+
+            (json, libraries, processors)
+        };
+
+        libraries.sort_by(|a, b| a.path.cmp(&b.path));
+        libraries.dedup_by(|a, b| a.path == b.path);
+
+        let id = json.get("id").and_then(|i| i.as_str()).ok_or("version JSON has no id")?.to_string();
+        Ok(LoaderProfile{id, json, libraries, installer: Some(installer), embedded, processors})
+    }
+
+    // Writes version JSON into `minecraft/versions` and puts libraries into `minecraft/libraries`
+    pub async fn install(&self, minecraft: &Path, profile: &LoaderProfile,
+                         quiet: bool) -> RResult<LoaderInstallReport> {
+        if profile.processors > 0 {
+            return Err(format!("installer of {} has {} processors patching the game jar, McGet can't run them, \
+                               use the installer itself", profile.id, profile.processors).into());
+        }
+
+        let libraries = minecraft.join("libraries");
+        let mut downloader = MassDownloader::new().with_quiet(quiet);
+        for lib in &profile.libraries {
+            let dest = libraries.join(&lib.path);
+            let url = match (&self.urls.libraries, &lib.url) {
+                (_, None) => { continue; },
+                (Some(mirror), Some(_)) => join_url(mirror, &lib.path),
+                (None, Some(url)) => url.clone()
+            };
+
+            // Downloader names files after URL, so they have to match the maven path
+            if url_filename(&url) != dest.file_name().unwrap().to_string_lossy() {
+                return Err(format!("library {} can't be downloaded from {}, file names differ", lib.path, url).into());
+            }
+
+            let mut target = DownloadTarget::with_url(url, dest.parent().unwrap().to_string_lossy().to_string());
+            target.expected.sha1 = lib.sha1.clone();
+            downloader.add_target(target);
+        }
+
+        // Installer's own maven repository and legacy universal jar
+        let mut extracted = 0usize;
+        if let Some(installer) = &profile.installer {
+            let mut archive = ZipArchive::new(Cursor::new(installer.as_slice()))?;
+            for (name, path) in &profile.embedded {
+                let dest = libraries.join(path);
+                if dest.exists() {
+                    continue;
+                }
+
+                let contents = read_zip_entry(&mut archive, name).ok_or(format!("installer has no {}", name))?;
+                std::fs::create_dir_all(dest.parent().unwrap())?;
+                std::fs::write(&dest, contents)?;
+                extracted += 1;
+            }
+        }
+
+        let download = downloader.download(&CurseForge::new(), GameVersion::new(String::new())).await;
+        for entry in download.entries.iter().filter(|e| !e.is_failed()) {
+            let lib = profile.libraries.iter().find(
+                |l| entry.path.as_ref().map(|p| p.ends_with(&l.path)).unwrap_or(false)
+            );
+            let expected = lib.and_then(|l| l.sha1.as_ref());
+            if expected.is_some() && expected != entry.sha1.as_ref() {
+                let path = entry.path.as_ref().unwrap();
+                std::fs::remove_file(path).unwrap_or_default();
+                return Err(format!("checksum mismatch of {}", path).into());
+            }
+        }
+
+        if let Some(failed) = download.failed().next() {
+            return Err(format!("failed to download library {}", failed.name()).into());
+        }
+
+        let versions = minecraft.join("versions").join(&profile.id);
+        std::fs::create_dir_all(&versions)?;
+        let version_json = versions.join(format!("{}.json", profile.id));
+        std::fs::write(&version_json, serde_json::to_string_pretty(&profile.json)?)?;
+
+        Ok(LoaderInstallReport{
+            id: profile.id.clone(),
+            version_json: version_json.to_string_lossy().to_string(),
+            libraries: profile.libraries.len(),
+            extracted,
+            download
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maven_path_of_plain_coordinates() {
+        assert_eq!(maven_path("net.minecraftforge:forge:1.20.1-47.2.0").as_deref(),
+                   Some("net/minecraftforge/forge/1.20.1-47.2.0/forge-1.20.1-47.2.0.jar"));
+        assert_eq!(maven_path("org.ow2.asm:asm:9.5").as_deref(), Some("org/ow2/asm/asm/9.5/asm-9.5.jar"));
+    }
+
+    #[test]
+    fn maven_path_with_classifier_and_extension() {
+        assert_eq!(maven_path("net.minecraftforge:forge:1.20.1-47.2.0:universal").as_deref(),
+                   Some("net/minecraftforge/forge/1.20.1-47.2.0/forge-1.20.1-47.2.0-universal.jar"));
+        assert_eq!(maven_path("de.oceanlabs.mcp:mcp_config:1.20.1-20230612.114412@zip").as_deref(),
+                   Some("de/oceanlabs/mcp/mcp_config/1.20.1-20230612.114412/mcp_config-1.20.1-20230612.114412.zip"));
+        assert_eq!(maven_path("net.minecraft:client:1.20.1:mappings@txt").as_deref(),
+                   Some("net/minecraft/client/1.20.1/client-1.20.1-mappings.txt"));
+    }
+
+    #[test]
+    fn maven_path_rejects_incomplete_coordinates() {
+        assert_eq!(maven_path("net.minecraftforge:forge"), None);
+        assert_eq!(maven_path(""), None);
+    }
+}
@@ -0,0 +1,304 @@
+use {
+    std::cmp::Ordering,
+    serde::{Serialize, Deserialize},
+    crate::version_range::VersionRange
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PreRelease {
+    Pre(u32),
+    Rc(u32)
+}
+
+// Minecraft version: release (1.20.1), pre-release (1.20-pre1), release candidate (1.20-rc1)
+// or snapshot (23w14a)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum McVersion {
+    Release { major: u32, minor: u32, patch: u32, pre: Option<PreRelease> },
+    Snapshot { year: u32, week: u32, letter: char }
+}
+
+impl McVersion {
+    pub fn parse(version: &str) -> Option<McVersion> {
+        let version = version.trim().to_lowercase();
+
+        if let Some((year, rest)) = version.split_once('w') {
+            let letter = rest.chars().last()?;
+            let week = rest.strip_suffix(letter)?;
+            if year.len() == 2 && letter.is_ascii_lowercase() {
+                return Some(McVersion::Snapshot{year: year.parse().ok()?, week: week.parse().ok()?, letter});
+            }
+        }
+
+        // `1.20-pre1`, `1.20-rc-1`, `1.20 pre-release 1`, `1.20 release candidate 1`
+        let (release, pre) = match version.find(['-', ' ']) {
+            Some(pos) => (&version[..pos], Some(&version[pos + 1..])),
+            None => (version.as_str(), None)
+        };
+
+        let pre = match pre {
+            Some(pre) => {
+                let number: String = pre.chars().filter(|c| c.is_ascii_digit()).collect();
+                let number = number.parse().ok()?;
+                if pre.starts_with("pre") {
+                    Some(PreRelease::Pre(number))
+                } else if pre.starts_with("rc") || pre.starts_with("release candidate") {
+                    Some(PreRelease::Rc(number))
+                } else {
+                    return None;
+                }
+            },
+            None => None
+        };
+
+        let mut parts = release.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next()?.parse().ok()?;
+        let patch = match parts.next() {
+            Some(patch) => patch.parse().ok()?,
+            None => 0
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(McVersion::Release{major, minor, patch, pre})
+    }
+
+    pub fn is_snapshot(&self) -> bool {
+        matches!(self, McVersion::Snapshot{..})
+    }
+
+    pub fn is_release(&self) -> bool {
+        matches!(self, McVersion::Release{pre: None, ..})
+    }
+
+    // Same `major.minor` of two releases, e.g. 1.20 and 1.20.1
+    pub fn same_minor(&self, other: &McVersion) -> bool {
+        match (self, other) {
+            (McVersion::Release{major: a, minor: b, ..}, McVersion::Release{major: c, minor: d, ..}) => a == c && b == d,
+            _ => false
+        }
+    }
+}
+
+impl std::fmt::Display for McVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            McVersion::Snapshot{year, week, letter} => write!(f, "{}w{:02}{}", year, week, letter),
+            McVersion::Release{major, minor, patch, pre} => {
+                write!(f, "{}.{}", major, minor)?;
+                if *patch > 0 {
+                    write!(f, ".{}", patch)?;
+                }
+
+                match pre {
+                    Some(PreRelease::Pre(n)) => write!(f, "-pre{}", n),
+                    Some(PreRelease::Rc(n)) => write!(f, "-rc{}", n),
+                    None => Ok(())
+                }
+            }
+        }
+    }
+}
+
+// Releases are ordered by number with pre-releases and release candidates before the release,
+// snapshots only among themselves since they aren't numbered after releases
+impl PartialOrd for McVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (McVersion::Release{major: a, minor: b, patch: c, pre: x},
+             McVersion::Release{major: d, minor: e, patch: f, pre: y}) => {
+                let pre_order = match (x, y) {
+                    (None, None) => Ordering::Equal,
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (Some(x), Some(y)) => x.cmp(y)
+                };
+                Some((a, b, c).cmp(&(d, e, f)).then(pre_order))
+            },
+            (McVersion::Snapshot{year: a, week: b, letter: c},
+             McVersion::Snapshot{year: d, week: e, letter: f}) => Some((a, b, c).cmp(&(d, e, f))),
+            _ => None
+        }
+    }
+}
+
+// How file version tags are matched against the pack version
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum VersionPolicy {
+    #[default]
+    Exact,
+
+    // Files tagged with another release of the same minor version are accepted too,
+    // e.g. a file for 1.20 in 1.20.1 pack
+    SameMinor
+}
+
+impl VersionPolicy {
+    pub fn is_exact(&self) -> bool {
+        *self == VersionPolicy::Exact
+    }
+}
+
+// Exact version, range (`>=1.19 <1.20.2`, `[1.19,1.20)`) or wildcard (`1.19.x`, `*`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum McVersionReq {
+    Exact(McVersion),
+    Range(VersionRange),
+
+    // Neither version nor range, compared as a plain tag
+    Other(String)
+}
+
+impl McVersionReq {
+    pub fn parse(req: &str) -> McVersionReq {
+        if let Some(version) = McVersion::parse(req) {
+            return McVersionReq::Exact(version);
+        }
+
+        match VersionRange::parse(req) {
+            Some(range) => McVersionReq::Range(range),
+            None => McVersionReq::Other(req.trim().to_lowercase())
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        matches!(self, McVersionReq::Exact(_))
+    }
+
+    pub fn contains(&self, version: &McVersion) -> bool {
+        match self {
+            McVersionReq::Exact(exact) => exact == version,
+            McVersionReq::Range(_) if version.is_snapshot() => false,
+
+            // Pre-releases sort below their release, so `<1.21` would take `1.21-pre1` without this
+            McVersionReq::Range(range) => range.contains(&version.to_string()) && match version {
+                McVersion::Release{major, minor, patch, pre: Some(_)} => range.contains(
+                    &McVersion::Release{major: *major, minor: *minor, patch: *patch, pre: None}.to_string()
+                ),
+                _ => true
+            },
+            McVersionReq::Other(_) => false
+        }
+    }
+
+    // Whether file with version `tag` fits
+    pub fn matches_tag(&self, tag: &str, policy: VersionPolicy) -> bool {
+        let version = match McVersion::parse(tag) {
+            Some(version) => version,
+            None => { return matches!(self, McVersionReq::Other(other) if *other == tag.to_lowercase()); }
+        };
+
+        if self.contains(&version) {
+            return true;
+        }
+
+        if policy != VersionPolicy::SameMinor || !version.is_release() {
+            return false;
+        }
+
+        match self {
+            McVersionReq::Exact(exact) => exact.is_release() && exact.same_minor(&version),
+            McVersionReq::Range(range) => match version {
+                McVersion::Release{major, minor, ..} => range.intersects(
+                    &format!("{}.{}", major, minor), &format!("{}.{}", major, minor + 1)
+                ),
+                _ => false
+            },
+            McVersionReq::Other(_) => false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(major: u32, minor: u32, patch: u32, pre: Option<PreRelease>) -> McVersion {
+        McVersion::Release{major, minor, patch, pre}
+    }
+
+    fn version(v: &str) -> McVersion {
+        McVersion::parse(v).unwrap()
+    }
+
+    #[test]
+    fn parses_releases_and_pre_releases() {
+        assert_eq!(McVersion::parse("1.20.1"), Some(release(1, 20, 1, None)));
+        assert_eq!(McVersion::parse("1.20"), Some(release(1, 20, 0, None)));
+        assert_eq!(McVersion::parse("1.20-pre1"), Some(release(1, 20, 0, Some(PreRelease::Pre(1)))));
+        assert_eq!(McVersion::parse("1.20-rc-1"), Some(release(1, 20, 0, Some(PreRelease::Rc(1)))));
+        assert_eq!(McVersion::parse("1.20 Pre-Release 2"), Some(release(1, 20, 0, Some(PreRelease::Pre(2)))));
+        assert_eq!(McVersion::parse("1.20.5 Release Candidate 3"), Some(release(1, 20, 5, Some(PreRelease::Rc(3)))));
+    }
+
+    #[test]
+    fn parses_snapshots() {
+        assert_eq!(McVersion::parse("23w14a"), Some(McVersion::Snapshot{year: 23, week: 14, letter: 'a'}));
+        assert_eq!(version("24w04b").to_string(), "24w04b");
+        assert!(version("23w14a").is_snapshot());
+    }
+
+    #[test]
+    fn rejects_other_strings() {
+        assert_eq!(McVersion::parse("1.20.1.1"), None);
+        assert_eq!(McVersion::parse("1.20-beta"), None);
+        assert_eq!(McVersion::parse("Forge"), None);
+        assert_eq!(McVersion::parse("1.20.x"), None);
+    }
+
+    #[test]
+    fn displays_versions() {
+        assert_eq!(version("1.20.0").to_string(), "1.20");
+        assert_eq!(version("1.20.1").to_string(), "1.20.1");
+        assert_eq!(version("1.20 Pre-Release 2").to_string(), "1.20-pre2");
+    }
+
+    #[test]
+    fn orders_releases() {
+        assert!(version("1.20-pre1") < version("1.20-pre2"));
+        assert!(version("1.20-pre2") < version("1.20-rc1"));
+        assert!(version("1.20-rc1") < version("1.20"));
+        assert!(version("1.20") < version("1.20.1"));
+        assert!(version("1.9.4") < version("1.10"));
+        assert!(version("23w14a") < version("23w14b"));
+        assert!(version("22w45a") < version("23w03a"));
+        assert_eq!(version("23w14a").partial_cmp(&version("1.20")), None);
+    }
+
+    #[test]
+    fn ranges_exclude_pre_releases_of_upper_bound() {
+        let req = McVersionReq::parse("1.20.x");
+        assert!(req.contains(&version("1.20")));
+        assert!(req.contains(&version("1.20.4")));
+        assert!(!req.contains(&version("1.21-pre1")));
+        assert!(!req.contains(&version("1.21")));
+
+        assert!(!McVersionReq::parse("[1.19,1.20)").contains(&version("1.20-rc1")));
+        assert!(McVersionReq::parse("<=1.21").contains(&version("1.21-pre1")));
+        assert!(!McVersionReq::parse(">=1.19").contains(&version("23w14a")));
+    }
+
+    #[test]
+    fn matches_tags() {
+        let exact = McVersionReq::parse("1.20.1");
+        assert!(exact.is_exact());
+        assert!(exact.matches_tag("1.20.1", VersionPolicy::Exact));
+        assert!(!exact.matches_tag("1.20", VersionPolicy::Exact));
+        assert!(exact.matches_tag("1.20", VersionPolicy::SameMinor));
+        assert!(!exact.matches_tag("1.19.4", VersionPolicy::SameMinor));
+
+        let range = McVersionReq::parse(">=1.20.2 <1.20.5");
+        assert!(range.matches_tag("1.20.3", VersionPolicy::Exact));
+        assert!(!range.matches_tag("1.20", VersionPolicy::Exact));
+        assert!(range.matches_tag("1.20", VersionPolicy::SameMinor));
+        assert!(range.matches_tag("1.20.6", VersionPolicy::SameMinor));
+        assert!(!range.matches_tag("1.21", VersionPolicy::SameMinor));
+        assert!(McVersionReq::parse(">=1.20.12").matches_tag("1.20.1", VersionPolicy::SameMinor));
+        assert!(!McVersionReq::parse("<1.20").matches_tag("1.20.1", VersionPolicy::SameMinor));
+
+        assert!(!McVersionReq::parse("1.20.x").matches_tag("Forge", VersionPolicy::Exact));
+    }
+}
@@ -0,0 +1,166 @@
+use {
+    std::str::FromStr,
+    serde::{Serialize, Deserialize, Serializer, Deserializer}
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ModLoader {
+    #[default]
+    Forge,
+    NeoForge,
+    Fabric,
+    Quilt,
+    LiteLoader,
+    Rift,
+    Unknown(String)
+}
+
+impl ModLoader {
+    // Case insensitive, with common aliases, e.g. `neoforged` or `fabric-loader`
+    pub fn parse(name: &str) -> ModLoader {
+        Self::known(name).unwrap_or_else(|| ModLoader::Unknown(name.trim().to_string()))
+    }
+
+    // Loader named by `name`, None for unknown names, e.g. game versions in file version tags
+    pub fn known(name: &str) -> Option<ModLoader> {
+        let name = name.trim().to_lowercase();
+        match name.as_str() {
+            "forge" | "minecraftforge" | "lexforge" => Some(ModLoader::Forge),
+            "neoforge" | "neoforged" | "neo-forge" | "neo" => Some(ModLoader::NeoForge),
+            "fabric" | "fabricmc" | "fabric-loader" | "fabricloader" => Some(ModLoader::Fabric),
+            "quilt" | "quiltmc" | "quilt-loader" | "quilt_loader" => Some(ModLoader::Quilt),
+            "liteloader" | "lite-loader" => Some(ModLoader::LiteLoader),
+            "rift" => Some(ModLoader::Rift),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            ModLoader::Forge => "forge",
+            ModLoader::NeoForge => "neoforge",
+            ModLoader::Fabric => "fabric",
+            ModLoader::Quilt => "quilt",
+            ModLoader::LiteLoader => "liteloader",
+            ModLoader::Rift => "rift",
+            ModLoader::Unknown(name) => name
+        }
+    }
+
+    // Whether mods built for `other` run on this loader, Quilt loads Fabric mods
+    pub fn can_load(&self, other: &ModLoader) -> bool {
+        match (self, other) {
+            (ModLoader::Quilt, ModLoader::Fabric) => true,
+            (ModLoader::Unknown(a), ModLoader::Unknown(b)) => a.eq_ignore_ascii_case(b),
+            (a, b) => a == b
+        }
+    }
+}
+
+impl FromStr for ModLoader {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::parse(s))
+    }
+}
+
+impl std::fmt::Display for ModLoader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Serialize for ModLoader {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for ModLoader {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::parse(&String::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::objects::ModFile};
+
+    fn file(versions: &[&str]) -> ModFile {
+        serde_json::from_value(serde_json::json!({
+            "id": 1, "fileName": "mod.jar", "gameVersion": versions, "downloadUrl": "", "dependencies": []
+        })).unwrap()
+    }
+
+    #[test]
+    fn parses_names_and_aliases() {
+        let cases = [
+            ("forge", ModLoader::Forge),
+            ("MinecraftForge", ModLoader::Forge),
+            ("lexforge", ModLoader::Forge),
+            ("NeoForge", ModLoader::NeoForge),
+            ("neoforged", ModLoader::NeoForge),
+            ("neo-forge", ModLoader::NeoForge),
+            (" fabric ", ModLoader::Fabric),
+            ("fabric-loader", ModLoader::Fabric),
+            ("FabricMC", ModLoader::Fabric),
+            ("quilt_loader", ModLoader::Quilt),
+            ("quiltmc", ModLoader::Quilt),
+            ("LiteLoader", ModLoader::LiteLoader),
+            ("rift", ModLoader::Rift)
+        ];
+        for (name, loader) in cases {
+            assert_eq!(ModLoader::known(name), Some(loader.clone()), "{}", name);
+            assert_eq!(ModLoader::parse(name), loader, "{}", name);
+        }
+    }
+
+    #[test]
+    fn keeps_unknown_names() {
+        for name in ["1.20.1", "Client", "risugami"] {
+            assert_eq!(ModLoader::known(name), None, "{}", name);
+        }
+        assert_eq!(ModLoader::parse(" Risugami "), ModLoader::Unknown("Risugami".to_string()));
+        assert_eq!(ModLoader::parse("Risugami").name(), "Risugami");
+    }
+
+    #[test]
+    fn checks_which_mods_loader_runs() {
+        let cases = [
+            (ModLoader::Quilt, ModLoader::Fabric, true),
+            (ModLoader::Quilt, ModLoader::Quilt, true),
+            (ModLoader::Fabric, ModLoader::Quilt, false),
+            (ModLoader::NeoForge, ModLoader::NeoForge, true),
+            (ModLoader::NeoForge, ModLoader::Forge, false),
+            (ModLoader::Forge, ModLoader::NeoForge, false),
+            (ModLoader::Forge, ModLoader::Fabric, false),
+            (ModLoader::parse("risugami"), ModLoader::parse("Risugami"), true),
+            (ModLoader::parse("risugami"), ModLoader::Forge, false)
+        ];
+        for (loader, other, expected) in cases {
+            assert_eq!(loader.can_load(&other), expected, "{} loading {} mods", loader, other);
+        }
+    }
+
+    #[test]
+    fn finds_loaders_among_version_tags() {
+        let cases: [(&[&str], Vec<ModLoader>); 5] = [
+            (&["1.20.1", "Forge"], vec![ModLoader::Forge]),
+            (&["Fabric", "1.20", "Client", "Quilt", "1.20.1"], vec![ModLoader::Fabric, ModLoader::Quilt]),
+            (&["NeoForge", "1.20.1", "Forge", "neoforge"], vec![ModLoader::NeoForge, ModLoader::Forge]),
+            (&["1.20.1", "Server", "Java 17"], vec![]),
+            (&[], vec![])
+        ];
+        for (versions, loaders) in cases {
+            assert_eq!(file(versions).mod_loaders(), loaders, "{:?}", versions);
+        }
+    }
+
+    #[test]
+    fn files_without_loader_tags_match_any_loader() {
+        assert!(file(&["1.20.1"]).has_mod_loader(&ModLoader::NeoForge));
+        assert!(file(&["Fabric", "1.20.1"]).has_mod_loader(&ModLoader::Quilt));
+        assert!(!file(&["Forge", "1.20.1"]).has_mod_loader(&ModLoader::NeoForge));
+    }
+}
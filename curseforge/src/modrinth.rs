@@ -0,0 +1,120 @@
+use {
+    std::collections::HashMap,
+    hyper::{Client, client::HttpConnector},
+    hyper_tls::HttpsConnector,
+    serde::{Serialize, Deserialize},
+    crate::{api::{RResult, web_post, web_get}, side::Side}
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModrinthHashes {
+    pub sha1: String,
+    pub sha512: String
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModrinthFile {
+    pub hashes: ModrinthHashes,
+    pub url: String,
+    pub filename: String,
+
+    #[serde(default)]
+    pub primary: bool,
+    pub size: usize
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModrinthVersion {
+    pub id: String,
+    pub project_id: String,
+    pub name: String,
+    pub version_number: String,
+    pub files: Vec<ModrinthFile>
+}
+
+impl ModrinthVersion {
+    pub fn file_by_sha512(&self, sha512: &str) -> Option<&ModrinthFile> {
+        self.files.iter().find(|f| f.hashes.sha512 == sha512)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModrinthProject {
+    pub id: String,
+    pub slug: String,
+    pub title: String,
+
+    // required, optional or unsupported
+    pub client_side: String,
+    pub server_side: String
+}
+
+impl ModrinthProject {
+    pub fn side(&self) -> Side {
+        Side::from_modrinth(&self.client_side, &self.server_side)
+    }
+}
+
+#[derive(Serialize)]
+struct HashLookup<'a> {
+    hashes: &'a [String],
+    algorithm: &'a str
+}
+
+// Client of Modrinth API, used only to identify files by their hashes and find out their sides
+#[derive(Clone)]
+pub struct Modrinth {
+    client: Client<HttpsConnector<HttpConnector>>
+}
+
+impl Modrinth {
+    // Versions containing files with given hashes, `algorithm` is sha1 or sha512
+    pub async fn version_files(&self, hashes: &[String],
+                               algorithm: &str) -> RResult<HashMap<String, ModrinthVersion>> {
+        let url = "https://api.modrinth.com/v2/version_files";
+        let body = serde_json::to_string(&HashLookup{hashes, algorithm})?;
+
+        let response = web_post(&self.client, url, body).await?;
+        let b_bytes = hyper::body::to_bytes(response.into_body()).await?;
+
+        Ok( serde_json::from_slice(&b_bytes)? )
+    }
+
+    pub async fn projects(&self, ids: &[String]) -> RResult<Vec<ModrinthProject>> {
+        let ids = serde_json::to_string(ids)?;
+        let url = format!("https://api.modrinth.com/v2/projects?ids={}", urlencoding::encode(&ids));
+
+        Ok( serde_json::from_slice(&web_get(&self.client, &url).await?)? )
+    }
+
+    // Sides of projects owning files with given sha512 hashes, unknown files are left out
+    pub async fn sides(&self, hashes: &[String]) -> RResult<HashMap<String, Side>> {
+        if hashes.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let versions = self.version_files(hashes, "sha512").await?;
+        let mut ids: Vec<String> = versions.values().map(|v| v.project_id.clone()).collect();
+        ids.sort();
+        ids.dedup();
+        let projects = self.projects(&ids).await?;
+
+        Ok( versions.into_iter().filter_map(|(hash, version)| {
+            let project = projects.iter().find(|p| p.id == version.project_id)?;
+            Some((hash, project.side()))
+        }).collect() )
+    }
+
+    pub fn new() -> Modrinth {
+        let https = HttpsConnector::new();
+        let client = Client::builder().build::<_, hyper::Body>(https);
+
+        Self{client}
+    }
+}
+
+impl Default for Modrinth {
+    fn default() -> Self {
+        Self::new()
+    }
+}
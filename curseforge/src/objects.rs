@@ -1,19 +1,21 @@
 use {
-    serde::Deserialize
+    serde::{Serialize, Deserialize},
+    crate::mod_loader::ModLoader,
+    crate::mc_version::{McVersionReq, VersionPolicy}
 };
 
 pub trait ModExt {
     fn search_mods<'vm>(&'vm self, version: &str, 
-                       loader: Option<&str>) -> Vec<&'vm ModFile>;
+                       loader: Option<&ModLoader>) -> Vec<&'vm ModFile>;
     fn where_mods(self, version: &str,
-                  loader: Option<&String>) -> Vec<ModFile>;
+                  loader: Option<&ModLoader>) -> Vec<ModFile>;
     
     fn latest<'vm>(&'vm self) -> Result<&'vm ModFile, ()>;
 }
 
 impl ModExt for Vec<ModFile> {
     fn search_mods<'vm>(&'vm self, version: &str, 
-                       loader: Option<&str>) -> Vec<&'vm ModFile> {
+                       loader: Option<&ModLoader>) -> Vec<&'vm ModFile> {
         self.iter().filter(
             move |mf| mf.has_version(version) &&
                       if loader.is_some() { mf.has_mod_loader(loader.unwrap()) } else { true }
@@ -21,7 +23,7 @@ impl ModExt for Vec<ModFile> {
     }
 
     fn where_mods(self, version: &str,
-                  loader: Option<&String>) -> Vec<ModFile> {
+                  loader: Option<&ModLoader>) -> Vec<ModFile> {
         let mut results = vec![];
         for v in self {
             if loader.is_some() && !v.has_mod_loader(loader.unwrap()) {
@@ -47,13 +49,103 @@ impl ModExt for Vec<ModFile> {
     }
 }
 
+// Type of CurseForge project and of modpack entry
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectKind {
+    #[default]
+    Mod,
+    ResourcePack,
+    ShaderPack,
+    DataPack,
+    World
+}
+
+impl std::str::FromStr for ProjectKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "mod" | "mods" => Ok(ProjectKind::Mod),
+            "resourcepack" | "resourcepacks" | "texturepack" => Ok(ProjectKind::ResourcePack),
+            "shaderpack" | "shaderpacks" | "shader" => Ok(ProjectKind::ShaderPack),
+            "datapack" | "datapacks" => Ok(ProjectKind::DataPack),
+            "world" | "worlds" | "save" => Ok(ProjectKind::World),
+            _ => Err(format!("unknown kind {}, expected mod, resourcepack, shaderpack, datapack or world", s))
+        }
+    }
+}
+
+impl std::fmt::Display for ProjectKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ProjectKind::Mod => "mod",
+            ProjectKind::ResourcePack => "resourcepack",
+            ProjectKind::ShaderPack => "shaderpack",
+            ProjectKind::DataPack => "datapack",
+            ProjectKind::World => "world"
+        })
+    }
+}
+
+impl ProjectKind {
+    pub fn is_mod(&self) -> bool {
+        *self == ProjectKind::Mod
+    }
+
+    // Section (class) id on CurseForge
+    pub fn section_id(&self) -> usize {
+        match self {
+            ProjectKind::Mod => 6,
+            ProjectKind::ResourcePack => 12,
+            ProjectKind::ShaderPack => 6552,
+            ProjectKind::DataPack => 6945,
+            ProjectKind::World => 17
+        }
+    }
+
+    pub fn from_section_id(id: usize) -> Option<ProjectKind> {
+        [ProjectKind::Mod, ProjectKind::ResourcePack, ProjectKind::ShaderPack,
+         ProjectKind::DataPack, ProjectKind::World].into_iter().find(|k| k.section_id() == id)
+    }
+
+    // Game directory files of this kind are installed into
+    pub fn dir(&self) -> &'static str {
+        match self {
+            ProjectKind::Mod => "mods",
+            ProjectKind::ResourcePack => "resourcepacks",
+            ProjectKind::ShaderPack => "shaderpacks",
+            ProjectKind::DataPack => "datapacks",
+            ProjectKind::World => "saves"
+        }
+    }
+
+    // Kind of file installed into game directory `dir`
+    pub fn from_dir(dir: &str) -> Option<ProjectKind> {
+        [ProjectKind::Mod, ProjectKind::ResourcePack, ProjectKind::ShaderPack,
+         ProjectKind::DataPack, ProjectKind::World].into_iter().find(|k| k.dir() == dir)
+    }
+
+    // Path part of project page URL on curseforge.com
+    pub fn url_section(&self) -> &'static str {
+        match self {
+            ProjectKind::Mod => "mc-mods",
+            ProjectKind::ResourcePack => "texture-packs",
+            ProjectKind::ShaderPack => "shaders",
+            ProjectKind::DataPack => "data-packs",
+            ProjectKind::World => "worlds"
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct GameVersion {
     pub version: String,
-    pub mod_loader: Option<String>
+    pub mod_loader: Option<ModLoader>,
+    pub policy: VersionPolicy
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ModDependency {
     #[serde(rename = "addonId")]
     pub addon_id: usize,
@@ -62,7 +154,7 @@ pub struct ModDependency {
     pub type_: usize
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ModFile {
     pub id: usize,
 
@@ -75,16 +167,64 @@ pub struct ModFile {
     #[serde(rename = "downloadUrl")]
     pub download_url: String,
 
+    // See `curseforge_fingerprint`
+    #[serde(rename = "packageFingerprint", default)]
+    pub fingerprint: Option<u32>,
+
+    #[serde(rename = "fileLength", default)]
+    pub length: Option<usize>,
+
+    // 1 - release, 2 - beta, 3 - alpha
+    #[serde(rename = "releaseType", default)]
+    pub release_type: usize,
+
+    // Upload time in ISO 8601, e.g. 2023-06-12T17:42:10.6Z
+    #[serde(rename = "fileDate", default)]
+    pub date: String,
+
     pub dependencies: Vec<ModDependency>
 }
 
-#[derive(Deserialize, Debug)]
+// Seconds part and fraction of upload time, fractions have any number of digits
+fn upload_time(date: &str) -> Option<(&str, f64)> {
+    let date = date.trim_end_matches('Z');
+    if date.len() < 19 || !date.is_char_boundary(19) {
+        return None;
+    }
+
+    let (seconds, fraction) = date.split_at(19);
+    Some((seconds, format!("0{}", fraction).parse().unwrap_or(0.0)))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FingerprintMatch {
+    // Mod id
+    pub id: usize,
+    pub file: ModFile
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FingerprintResult {
+    #[serde(rename = "exactMatches", default)]
+    pub exact_matches: Vec<FingerprintMatch>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CategorySection {
+    #[serde(rename = "gameCategoryId")]
+    pub game_category_id: usize
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Mod {
     pub id: usize,
 
     pub name: String,
     pub summary: String,
 
+    #[serde(default)]
+    pub slug: String,
+
     #[serde(rename = "websiteUrl")]
     pub curseforge: String,
 
@@ -92,38 +232,94 @@ pub struct Mod {
     pub mod_loaders: Option<Vec<String>>,
 
     #[serde(rename = "latestFiles")]
-    pub latest_files: Vec<ModFile>
+    pub latest_files: Vec<ModFile>,
+
+    #[serde(rename = "categorySection", default)]
+    pub category_section: Option<CategorySection>
+}
+
+impl Mod {
+    pub fn kind(&self) -> ProjectKind {
+        self.category_section.as_ref()
+            .and_then(|s| ProjectKind::from_section_id(s.game_category_id))
+            .unwrap_or_default()
+    }
 }
 
 impl GameVersion {
     pub fn new(version: String) -> Self {
-        GameVersion{version, mod_loader: None}
+        GameVersion{version, mod_loader: None, policy: VersionPolicy::Exact}
     }
 
-    pub fn with_loader(mut self, loader: String) -> Self {
+    pub fn with_policy(mut self, policy: VersionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn matches(&self, file: &ModFile) -> bool {
+        let loader_ok = match &self.mod_loader {
+            Some(loader) => file.has_mod_loader(loader),
+            None => true
+        };
+
+        loader_ok && file.matches_version(&McVersionReq::parse(&self.version), self.policy)
+    }
+
+    pub fn with_loader(mut self, loader: ModLoader) -> Self {
         self.mod_loader = Option::Some(loader);
         self
     }
+
+    // Only mods depend on mod loader
+    pub fn for_kind(mut self, kind: ProjectKind) -> Self {
+        if !kind.is_mod() {
+            self.mod_loader = None;
+        }
+        self
+    }
 }
 
 impl ModFile {
+    // `ver` may be an exact version, a range or a wildcard
     pub fn has_version(&self, ver: &str) -> bool {
-        let lower = ver.to_lowercase();
-        let had = self.versions.iter().any(move |v| v.to_lowercase() == lower);
-        had
+        self.matches_version(&McVersionReq::parse(ver), VersionPolicy::Exact)
+    }
+
+    pub fn matches_version(&self, req: &McVersionReq, policy: VersionPolicy) -> bool {
+        self.versions.iter().any(|v| req.matches_tag(v, policy))
+    }
+
+    // Files without upload time are compared by ids, which only grow
+    pub fn is_newer_than(&self, other: &ModFile) -> bool {
+        match (upload_time(&self.date), upload_time(&other.date)) {
+            (Some(a), Some(b)) => a > b,
+            _ => self.id > other.id
+        }
+    }
+
+    pub fn is_beta(&self) -> bool {
+        self.release_type == 2 || self.release_type == 3
     }
 
     pub fn is_modloader(s: &str) -> bool {
-        s == "Forge" || s == "Fabric" || s == "LiteLoader"
+        ModLoader::known(s).is_some()
     }
 
-    pub fn has_mod_loader(&self, loader: &str) -> bool {
-        let lower = loader.to_lowercase();
-        if self.versions.is_empty() {
-            return true;
+    // Loaders among version tags, a file may list several of them anywhere between game versions
+    pub fn mod_loaders(&self) -> Vec<ModLoader> {
+        let mut loaders = vec![];
+        for loader in self.versions.iter().filter_map(|v| ModLoader::known(v)) {
+            if !loaders.contains(&loader) {
+                loaders.push(loader);
+            }
         }
-        let first = self.versions.first().unwrap();
 
-        !Self::is_modloader(first) || first.to_lowercase() == lower
+        loaders
+    }
+
+    // Files without loader tags are assumed to work with any loader
+    pub fn has_mod_loader(&self, loader: &ModLoader) -> bool {
+        let loaders = self.mod_loaders();
+        loaders.is_empty() || loaders.iter().any(|l| loader.can_load(l))
     }
 }
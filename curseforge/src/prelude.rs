@@ -1,4 +1,13 @@
 pub use crate::objects::*;
+pub use crate::mod_loader::*;
 pub use crate::api::*;
 pub use crate::downloader::*;
 pub use crate::dependency_resolver::*;
+pub use crate::hash::*;
+pub use crate::loader::*;
+pub use crate::version_range::*;
+pub use crate::mc_version::*;
+pub use crate::side::*;
+pub use crate::jar::*;
+pub use crate::modrinth::*;
+pub use crate::scanner::*;
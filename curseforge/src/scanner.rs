@@ -0,0 +1,566 @@
+use {
+    std::{
+        fs::File,
+        io::{Cursor, Read, Seek},
+        path::{Path, PathBuf}
+    },
+    serde::{Serialize, Deserialize},
+    zip::ZipArchive,
+
+    crate::prelude::RResult
+};
+
+// What happens to a jar the scanner flagged
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScanMode {
+    #[default]
+    Warn,
+
+    // Flagged jars are deleted and their download fails
+    Block
+}
+
+impl ScanMode {
+    pub fn is_warn(&self) -> bool {
+        *self == ScanMode::Warn
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScanSeverity {
+    Suspicious,
+    Malicious
+}
+
+impl std::fmt::Display for ScanSeverity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScanSeverity::Suspicious => write!(f, "suspicious"),
+            ScanSeverity::Malicious => write!(f, "malicious")
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ScanFinding {
+    pub severity: ScanSeverity,
+    pub rule: String,
+
+    // Class inside of the jar, nested jars are separated by `!/`
+    pub entry: String,
+    pub message: String
+}
+
+impl std::fmt::Display for ScanFinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}, {})", self.message, self.rule, self.entry)
+    }
+}
+
+// Scan result of a single jar of `scan_path`
+#[derive(Serialize)]
+pub struct JarScan {
+    pub file: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub findings: Vec<ScanFinding>
+}
+
+// Strings left in classes by known malware, mostly fractureiser stages
+const KNOWN_SIGNATURES: [(&str, &str); 6] = [
+    ("85.217.144.130", "fractureiser stage 0 server address"),
+    ("107.189.3.101", "fractureiser stage 1 server address"),
+    ("dev/neko/nekoclient", "fractureiser stage 3 client"),
+    ("dev/neko/nekoinjector", "fractureiser jar injector"),
+    ("libWebGL64.jar", "fractureiser stage 1 payload"),
+    ("systemd-utility", "fractureiser persistence service")
+];
+
+// Locations started with the system, nothing a mod should touch
+const STARTUP_LOCATIONS: [&str; 8] = [
+    "Start Menu\\Programs\\Startup",
+    "Start Menu/Programs/Startup",
+    "CurrentVersion\\Run",
+    "/etc/systemd/system",
+    ".config/systemd/user",
+    ".config/autostart",
+    "Library/LaunchAgents",
+    "/etc/init.d"
+];
+
+// Calls able to put something into startup location, `reg add` included
+const STARTUP_WRITES: [(&str, &str); 7] = [
+    ("java/io/FileOutputStream", "<init>"),
+    ("java/io/FileWriter", "<init>"),
+    ("java/nio/file/Files", "write"),
+    ("java/nio/file/Files", "writeString"),
+    ("java/nio/file/Files", "copy"),
+    ("java/nio/file/Files", "newOutputStream"),
+    ("java/lang/Runtime", "exec")
+];
+
+// Nested jars deeper than this aren't scanned and are reported
+const MAX_DEPTH: usize = 4;
+
+// Constant pool of class file reduced to what rules look at
+#[derive(Default)]
+struct ClassInfo {
+    utf8: Vec<String>,
+
+    // String literals
+    strings: Vec<String>,
+
+    // Referenced methods as (class, name, descriptor)
+    methods: Vec<(String, String, String)>
+}
+
+impl ClassInfo {
+    fn calls(&self, class: &str, name: &str) -> bool {
+        self.methods.iter().any(|(c, n, _)| c == class && n == name)
+    }
+
+    fn calls_with(&self, class: &str, name: &str, descriptor: &str) -> bool {
+        self.methods.iter().any(|(c, n, d)| c == class && n == name && d.starts_with(descriptor))
+    }
+}
+
+struct ClassReader<'a> {
+    data: &'a [u8],
+    pos: usize
+}
+
+impl<'a> ClassReader<'a> {
+    fn bytes(&mut self, count: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos + count)?;
+        self.pos += count;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+}
+
+enum Constant {
+    Utf8(String),
+    Class(u16),
+    String(u16),
+    Member(u16, u16),
+    NameAndType(u16, u16),
+    Other
+}
+
+fn parse_class(data: &[u8]) -> Option<ClassInfo> {
+    let mut reader = ClassReader{data, pos: 0};
+    if reader.bytes(4)? != [0xCA, 0xFE, 0xBA, 0xBE] {
+        return None;
+    }
+    reader.bytes(4)?;
+
+    let count = reader.u16()? as usize;
+    let mut pool = Vec::with_capacity(count);
+    pool.push(Constant::Other);
+    while pool.len() < count {
+        let constant = match reader.u8()? {
+            1 => {
+                let len = reader.u16()? as usize;
+                Constant::Utf8(String::from_utf8_lossy(reader.bytes(len)?).to_string())
+            },
+            7 => Constant::Class(reader.u16()?),
+            8 => Constant::String(reader.u16()?),
+            9..=11 => Constant::Member(reader.u16()?, reader.u16()?),
+            12 => Constant::NameAndType(reader.u16()?, reader.u16()?),
+            3 | 4 | 17 | 18 => { reader.bytes(4)?; Constant::Other },
+            5 | 6 => {
+                // Longs and doubles take two slots
+                reader.bytes(8)?;
+                pool.push(Constant::Other);
+                Constant::Other
+            },
+            15 => { reader.bytes(3)?; Constant::Other },
+            16 | 19 | 20 => { reader.bytes(2)?; Constant::Other },
+            _ => { return None; }
+        };
+        pool.push(constant);
+    }
+
+    let utf8 = |index: u16| match pool.get(index as usize) {
+        Some(Constant::Utf8(s)) => Some(s.clone()),
+        _ => None
+    };
+    let class = |index: u16| match pool.get(index as usize) {
+        Some(Constant::Class(name)) => utf8(*name),
+        _ => None
+    };
+
+    let mut info = ClassInfo::default();
+    for constant in &pool {
+        match constant {
+            Constant::Utf8(s) => info.utf8.push(s.clone()),
+            Constant::String(index) => info.strings.extend(utf8(*index)),
+            Constant::Member(owner, nat) => {
+                if let (Some(owner), Some(Constant::NameAndType(name, descriptor))) = (class(*owner), pool.get(*nat as usize)) {
+                    if let (Some(name), Some(descriptor)) = (utf8(*name), utf8(*descriptor)) {
+                        info.methods.push((owner, name, descriptor));
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
+    Some(info)
+}
+
+// Findings of a single class file as (severity, rule, message)
+fn scan_class(data: &[u8]) -> Vec<(ScanSeverity, &'static str, String)> {
+    let info = match parse_class(data) {
+        Some(info) => info,
+        None => { return vec![]; }
+    };
+
+    let mut findings = vec![];
+    for (signature, name) in KNOWN_SIGNATURES {
+        if info.utf8.iter().any(|s| s.contains(signature)) {
+            findings.push((ScanSeverity::Malicious, "known-signature", format!("contains {}", name)));
+        }
+    }
+
+    // Stage 0 of fractureiser: class loaded from remote URL by names hidden in byte arrays
+    let remote_loader = info.calls("java/net/URLClassLoader", "<init>");
+    let loads_class = info.calls("java/lang/Class", "forName") || info.calls("java/lang/ClassLoader", "loadClass");
+    let hidden_strings = info.calls_with("java/lang/String", "<init>", "([B")
+        || info.calls_with("java/lang/String", "<init>", "([C")
+        || info.calls("java/util/Base64$Decoder", "decode");
+    if remote_loader && loads_class && hidden_strings {
+        if info.calls("java/lang/reflect/Method", "invoke") {
+            findings.push((ScanSeverity::Malicious, "stage-0-loader",
+                           "loads and runs remote class with obfuscated names".to_string()));
+        } else {
+            findings.push((ScanSeverity::Suspicious, "remote-class-loading",
+                           "loads remote class with obfuscated names".to_string()));
+        }
+    }
+
+    let writes = STARTUP_WRITES.iter().any(|(class, name)| info.calls(class, name));
+    if writes {
+        let location = info.strings.iter()
+            .find_map(|s| STARTUP_LOCATIONS.iter().find(|l| s.contains(*l)));
+        if let Some(location) = location {
+            findings.push((ScanSeverity::Malicious, "startup-persistence",
+                           format!("writes to startup location {}", location)));
+        }
+    }
+
+    findings
+}
+
+// Entry the scanner can't look into, e.g. compressed by unsupported method, is reported instead
+// of being taken as clean
+fn unscannable(entry: String, reason: impl std::fmt::Display) -> ScanFinding {
+    ScanFinding{severity: ScanSeverity::Suspicious, rule: "unscannable-entry".to_string(), entry,
+                message: format!("unscannable entry: {}", reason)}
+}
+
+fn scan_archive<R: Read + Seek>(archive: &mut ZipArchive<R>, prefix: &str, depth: usize,
+                                findings: &mut Vec<ScanFinding>) {
+    for i in 0..archive.len() {
+        // Raw entry has the name even if its compression can't be read
+        let name = match archive.by_index_raw(i) {
+            Ok(entry) => entry.name().to_string(),
+            Err(e) => {
+                findings.push(unscannable(format!("{}#{}", prefix, i), e));
+                continue;
+            }
+        };
+        if !name.ends_with(".class") && !name.ends_with(".jar") {
+            continue;
+        }
+
+        let entry = format!("{}{}", prefix, name);
+        let mut bytes = vec![];
+        if let Err(e) = archive.by_index(i).map_err(|e| e.to_string())
+            .and_then(|mut file| file.read_to_end(&mut bytes).map_err(|e| e.to_string())) {
+            findings.push(unscannable(entry, e));
+            continue;
+        }
+
+        if name.ends_with(".jar") {
+            if depth >= MAX_DEPTH {
+                findings.push(unscannable(entry, "jar is nested too deep"));
+                continue;
+            }
+            match ZipArchive::new(Cursor::new(bytes)) {
+                Ok(mut nested) => scan_archive(&mut nested, &format!("{}!/", entry), depth + 1, findings),
+                Err(e) => findings.push(unscannable(entry, e))
+            }
+            continue;
+        }
+
+        for (severity, rule, message) in scan_class(&bytes) {
+            findings.push(ScanFinding{severity, rule: rule.to_string(), entry: entry.clone(), message});
+        }
+    }
+}
+
+// Inspects every class of jar, including bundled jars
+pub fn scan_jar(path: &Path) -> RResult<Vec<ScanFinding>> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut findings = vec![];
+    scan_archive(&mut archive, "", 0, &mut findings);
+
+    Ok(findings)
+}
+
+fn collect_jars(path: &Path, jars: &mut Vec<PathBuf>) -> RResult<()> {
+    if path.is_dir() {
+        for entry in std::fs::read_dir(path)? {
+            collect_jars(&entry?.path(), jars)?;
+        }
+    } else if path.extension().map(|e| e == "jar").unwrap_or(false) {
+        jars.push(path.to_path_buf());
+    }
+
+    Ok(())
+}
+
+// Scans jar or every jar of directory recursively
+pub fn scan_path(path: &Path) -> RResult<Vec<JarScan>> {
+    let mut jars = vec![];
+    if path.is_file() {
+        jars.push(path.to_path_buf());
+    } else {
+        collect_jars(path, &mut jars)?;
+        jars.sort();
+    }
+
+    Ok(jars.into_iter().map(|jar| {
+        let file = jar.to_string_lossy().to_string();
+        match scan_jar(&jar) {
+            Ok(findings) => JarScan{file, error: None, findings},
+            Err(e) => JarScan{file, error: Some(e.to_string()), findings: vec![]}
+        }
+    }).collect())
+}
+
+// Scans jars written without the downloader, e.g. overrides: unreadable jars always fail,
+// flagged ones fail in `Block` mode and are returned otherwise
+pub fn check_path(path: &Path, scan: ScanMode) -> RResult<Vec<JarScan>> {
+    let flagged: Vec<JarScan> = scan_path(path)?.into_iter()
+        .filter(|j| j.error.is_some() || !j.findings.is_empty())
+        .collect();
+
+    for jar in &flagged {
+        if let Some(error) = &jar.error {
+            return Err(format!("malware scanner can't read {}: {}", jar.file, error).into());
+        }
+        if scan == ScanMode::Block {
+            return Err(format!("{} is blocked by malware scanner: {}", jar.file, jar.findings[0]).into());
+        }
+    }
+
+    Ok(flagged)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::io::Write,
+        zip::{ZipWriter, CompressionMethod, write::FileOptions}
+    };
+
+    // Constant pool of class file written entry by entry
+    #[derive(Default)]
+    struct Pool {
+        entries: Vec<u8>,
+        count: u16
+    }
+
+    impl Pool {
+        fn push(&mut self, bytes: &[u8]) -> u16 {
+            self.entries.extend_from_slice(bytes);
+            self.count += 1;
+            self.count
+        }
+
+        fn utf8(&mut self, value: &str) -> u16 {
+            let mut bytes = vec![1];
+            bytes.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            bytes.extend_from_slice(value.as_bytes());
+            self.push(&bytes)
+        }
+
+        fn string(&mut self, value: &str) -> u16 {
+            let index = self.utf8(value);
+            self.push(&[8, (index >> 8) as u8, index as u8])
+        }
+
+        fn method(&mut self, class: &str, name: &str, descriptor: &str) -> u16 {
+            let class_name = self.utf8(class);
+            let class = self.push(&[7, (class_name >> 8) as u8, class_name as u8]);
+            let (name, descriptor) = (self.utf8(name), self.utf8(descriptor));
+            let nat = self.push(&[12, (name >> 8) as u8, name as u8, (descriptor >> 8) as u8, descriptor as u8]);
+            self.push(&[10, (class >> 8) as u8, class as u8, (nat >> 8) as u8, nat as u8])
+        }
+
+        fn class(&self) -> Vec<u8> {
+            let mut bytes = vec![0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 61];
+            bytes.extend_from_slice(&(self.count + 1).to_be_bytes());
+            bytes.extend_from_slice(&self.entries);
+            bytes
+        }
+    }
+
+    fn rules(pool: &Pool) -> Vec<(ScanSeverity, &'static str)> {
+        scan_class(&pool.class()).into_iter().map(|(severity, rule, _)| (severity, rule)).collect()
+    }
+
+    fn remote_loader(pool: &mut Pool) {
+        pool.method("java/net/URLClassLoader", "<init>", "([Ljava/net/URL;)V");
+        pool.method("java/lang/Class", "forName", "(Ljava/lang/String;ZLjava/lang/ClassLoader;)Ljava/lang/Class;");
+    }
+
+    #[test]
+    fn parses_constant_pool() {
+        let mut pool = Pool::default();
+        pool.string("hello");
+        pool.method("java/lang/Runtime", "exec", "(Ljava/lang/String;)Ljava/lang/Process;");
+
+        let info = parse_class(&pool.class()).unwrap();
+        assert_eq!(info.strings, vec!["hello"]);
+        assert!(info.calls("java/lang/Runtime", "exec"));
+        assert!(info.calls_with("java/lang/Runtime", "exec", "(Ljava/lang/String;"));
+        assert!(!info.calls("java/lang/Runtime", "halt"));
+    }
+
+    #[test]
+    fn malformed_class_has_no_findings() {
+        let mut pool = Pool::default();
+        pool.utf8("dev/neko/nekoclient/Client");
+        let class = pool.class();
+
+        assert!(parse_class(&class[..class.len() - 4]).is_none());
+        assert!(parse_class(b"not a class").is_none());
+        assert!(scan_class(&class[..class.len() - 4]).is_empty());
+
+        let mut unknown = class.clone();
+        unknown[10] = 99;
+        assert!(parse_class(&unknown).is_none());
+    }
+
+    #[test]
+    fn known_signature() {
+        let mut pool = Pool::default();
+        pool.utf8("dev/neko/nekoclient/Client");
+        assert_eq!(rules(&pool), vec![(ScanSeverity::Malicious, "known-signature")]);
+
+        let mut pool = Pool::default();
+        pool.utf8("net/fabricmc/api/ModInitializer");
+        assert!(rules(&pool).is_empty());
+    }
+
+    #[test]
+    fn stage_0_loader() {
+        let mut pool = Pool::default();
+        remote_loader(&mut pool);
+        pool.method("java/lang/String", "<init>", "([BLjava/nio/charset/Charset;)V");
+        pool.method("java/lang/reflect/Method", "invoke", "(Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/Object;");
+        assert_eq!(rules(&pool), vec![(ScanSeverity::Malicious, "stage-0-loader")]);
+
+        // Names aren't hidden
+        let mut pool = Pool::default();
+        remote_loader(&mut pool);
+        pool.method("java/lang/reflect/Method", "invoke", "(Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/Object;");
+        assert!(rules(&pool).is_empty());
+    }
+
+    #[test]
+    fn remote_class_loading() {
+        let mut pool = Pool::default();
+        remote_loader(&mut pool);
+        pool.method("java/util/Base64$Decoder", "decode", "(Ljava/lang/String;)[B");
+        assert_eq!(rules(&pool), vec![(ScanSeverity::Suspicious, "remote-class-loading")]);
+
+        // Local class loading
+        let mut pool = Pool::default();
+        pool.method("java/lang/Class", "forName", "(Ljava/lang/String;)Ljava/lang/Class;");
+        pool.method("java/util/Base64$Decoder", "decode", "(Ljava/lang/String;)[B");
+        assert!(rules(&pool).is_empty());
+    }
+
+    #[test]
+    fn startup_persistence() {
+        let mut pool = Pool::default();
+        pool.method("java/io/FileOutputStream", "<init>", "(Ljava/lang/String;)V");
+        pool.string("AppData\\Roaming\\Microsoft\\Windows\\Start Menu\\Programs\\Startup\\run.bat");
+        assert_eq!(rules(&pool), vec![(ScanSeverity::Malicious, "startup-persistence")]);
+
+        let mut pool = Pool::default();
+        pool.method("java/io/FileOutputStream", "<init>", "(Ljava/lang/String;)V");
+        pool.string("config/sodium-options.json");
+        assert!(rules(&pool).is_empty());
+    }
+
+    fn jar(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+        for (name, contents) in entries {
+            zip.start_file(*name, FileOptions::default().compression_method(CompressionMethod::Stored)).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn scan(jar: Vec<u8>) -> Vec<ScanFinding> {
+        let mut findings = vec![];
+        scan_archive(&mut ZipArchive::new(Cursor::new(jar)).unwrap(), "", 0, &mut findings);
+        findings
+    }
+
+    #[test]
+    fn finds_classes_of_nested_jars() {
+        let mut pool = Pool::default();
+        pool.utf8("dev/neko/nekoinjector/Injector");
+        let inner = jar(&[("a/Inject.class", &pool.class())]);
+
+        let findings = scan(jar(&[("META-INF/jars/inner.jar", &inner), ("readme.txt", b"hi")]));
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule, "known-signature");
+        assert_eq!(findings[0].entry, "META-INF/jars/inner.jar!/a/Inject.class");
+    }
+
+    #[test]
+    fn reports_unscannable_entries() {
+        // Stored class marked as compressed by bzip2, which can't be read
+        let mut bzip2 = jar(&[("a/Hidden.class", b"\xCA\xFE\xBA\xBE")]);
+        let central = bzip2.windows(4).position(|w| w == [0x50, 0x4B, 0x01, 0x02]).unwrap();
+        bzip2[8] = 12;
+        bzip2[central + 10] = 12;
+
+        let findings = scan(bzip2);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule, "unscannable-entry");
+        assert_eq!(findings[0].entry, "a/Hidden.class");
+
+        let findings = scan(jar(&[("broken.jar", b"not a zip")]));
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].entry, "broken.jar");
+
+        let mut nested = jar(&[("a/Empty.class", b"")]);
+        for _ in 0..=MAX_DEPTH {
+            nested = jar(&[("inner.jar", &nested)]);
+        }
+        let findings = scan(nested);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule, "unscannable-entry");
+        assert!(findings[0].message.contains("too deep"));
+    }
+}
@@ -0,0 +1,59 @@
+use {
+    std::path::Path,
+    serde::{Serialize, Deserialize},
+    crate::jar::JarMeta
+};
+
+// Where pack entry has to be installed
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Client,
+    Server,
+    #[default]
+    Both
+}
+
+impl std::fmt::Display for Side {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Side::Client => "client",
+            Side::Server => "server",
+            Side::Both => "both"
+        })
+    }
+}
+
+impl Side {
+    pub fn on_client(&self) -> bool {
+        *self != Side::Server
+    }
+
+    pub fn on_server(&self) -> bool {
+        *self != Side::Client
+    }
+
+    // Modrinth `client_side`/`server_side` values: required, optional or unsupported
+    pub fn from_modrinth(client: &str, server: &str) -> Side {
+        match (client, server) {
+            ("unsupported", "unsupported") => Side::Both,
+            ("unsupported", _) => Side::Server,
+            (_, "unsupported") => Side::Client,
+            _ => Side::Both
+        }
+    }
+
+    // packwiz `side` value
+    pub fn from_packwiz(side: &str) -> Side {
+        match side {
+            "client" => Side::Client,
+            "server" => Side::Server,
+            _ => Side::Both
+        }
+    }
+
+    // Side declared in jar metadata, None if jar doesn't say anything
+    pub fn from_jar(path: &Path) -> Option<Side> {
+        JarMeta::read(path)?.side()
+    }
+}
@@ -0,0 +1,468 @@
+use std::cmp::Ordering;
+
+// Compares dotted versions numerically, e.g. 47.10.0 > 47.2; pre-releases (`-beta`) go before releases
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let split = |v: &str| {
+        let v = v.split('+').next().unwrap_or_default();
+        match v.split_once('-') {
+            Some((release, pre)) => (release.to_string(), Some(pre.to_string())),
+            None => (v.to_string(), None)
+        }
+    };
+
+    let ((a, a_pre), (b, b_pre)) = (split(a), split(b));
+    let a_parts: Vec<&str> = a.split('.').collect();
+    let b_parts: Vec<&str> = b.split('.').collect();
+
+    for idx in 0..a_parts.len().max(b_parts.len()) {
+        let x = a_parts.get(idx).copied().unwrap_or("0");
+        let y = b_parts.get(idx).copied().unwrap_or("0");
+        let order = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            _ => x.cmp(y)
+        };
+
+        if order != Ordering::Equal {
+            return order;
+        }
+    }
+
+    match (a_pre, b_pre) {
+        (None, None) => Ordering::Equal,
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(x), Some(y)) => x.cmp(&y)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoundOp {
+    Less,
+    LessEq,
+    Greater,
+    GreaterEq,
+    Eq
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bound {
+    pub op: BoundOp,
+    pub version: String
+}
+
+impl Bound {
+    fn new(op: BoundOp, version: &str) -> Bound {
+        Bound{op, version: version.trim().to_string()}
+    }
+
+    pub fn matches(&self, version: &str) -> bool {
+        let order = compare_versions(version, &self.version);
+        match self.op {
+            BoundOp::Less => order == Ordering::Less,
+            BoundOp::LessEq => order != Ordering::Greater,
+            BoundOp::Greater => order == Ordering::Greater,
+            BoundOp::GreaterEq => order != Ordering::Less,
+            BoundOp::Eq => order == Ordering::Equal
+        }
+    }
+
+    // Lower end of the bound, `(version, inclusive)`
+    fn lower(&self) -> Option<(&str, bool)> {
+        match self.op {
+            BoundOp::Greater => Some((&self.version, false)),
+            BoundOp::GreaterEq | BoundOp::Eq => Some((&self.version, true)),
+            _ => None
+        }
+    }
+
+    // Upper end of the bound, `(version, inclusive)`
+    fn upper(&self) -> Option<(&str, bool)> {
+        match self.op {
+            BoundOp::Less => Some((&self.version, false)),
+            BoundOp::LessEq | BoundOp::Eq => Some((&self.version, true)),
+            _ => None
+        }
+    }
+}
+
+// Version requirement: any of the alternatives, each is a set of bounds that all have to match.
+// Understands maven ranges (`[47.2,48)`), comparators (`>=0.15 <0.16`, `^1.2`, `~1.2`),
+// wildcards (`0.15.x`, `*`) and exact versions
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionRange {
+    pub alternatives: Vec<Vec<Bound>>
+}
+
+fn next_version(version: &str, position: usize) -> String {
+    let mut parts: Vec<u64> = version.split('.').map(|p| p.parse().unwrap_or(0)).collect();
+    parts.resize(position + 1, 0);
+    parts[position] += 1;
+
+    parts.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(".")
+}
+
+impl VersionRange {
+    pub fn any() -> VersionRange {
+        VersionRange{alternatives: vec![vec![]]}
+    }
+
+    pub fn exact(version: &str) -> VersionRange {
+        VersionRange{alternatives: vec![vec![Bound::new(BoundOp::Eq, version)]]}
+    }
+
+    pub fn parse(range: &str) -> Option<VersionRange> {
+        let range = range.trim();
+        if range.is_empty() || range == "*" {
+            return Some(Self::any());
+        }
+
+        if range.starts_with('[') || range.starts_with('(') {
+            return Self::parse_maven(range);
+        }
+
+        let mut alternatives = vec![];
+        for alternative in range.split("||") {
+            let mut bounds = vec![];
+            for token in alternative.split_whitespace() {
+                bounds.extend(Self::parse_comparator(token)?);
+            }
+            alternatives.push(bounds);
+        }
+
+        Some(VersionRange{alternatives})
+    }
+
+    fn parse_comparator(token: &str) -> Option<Vec<Bound>> {
+        let ops = [(">=", BoundOp::GreaterEq), ("<=", BoundOp::LessEq), (">", BoundOp::Greater),
+                   ("<", BoundOp::Less), ("=", BoundOp::Eq)];
+        for (prefix, op) in ops {
+            if let Some(version) = token.strip_prefix(prefix) {
+                return Some(vec![Bound::new(op, version)]);
+            }
+        }
+
+        if let Some(version) = token.strip_prefix('^') {
+            let major = version.split('.').next()?;
+            let position = if major == "0" { 1 } else { 0 };
+            return Some(vec![Bound::new(BoundOp::GreaterEq, version),
+                             Bound::new(BoundOp::Less, &next_version(version, position))]);
+        }
+
+        if let Some(version) = token.strip_prefix('~') {
+            return Some(vec![Bound::new(BoundOp::GreaterEq, version),
+                             Bound::new(BoundOp::Less, &next_version(version, 1))]);
+        }
+
+        let wildcard = token.split('.').position(|p| p == "x" || p == "X" || p == "*");
+        match wildcard {
+            Some(0) => Some(vec![]),
+            Some(position) => {
+                let prefix: Vec<&str> = token.split('.').take(position).collect();
+                let prefix = prefix.join(".");
+                Some(vec![Bound::new(BoundOp::GreaterEq, &prefix),
+                          Bound::new(BoundOp::Less, &next_version(&prefix, position - 1))])
+            },
+            None => Some(vec![Bound::new(BoundOp::Eq, token)])
+        }
+    }
+
+    // `[1.0,2.0)`, `[1.0,)`, `(,2.0]`, `[1.0]`, and several of them separated by commas
+    fn parse_maven(range: &str) -> Option<VersionRange> {
+        let mut alternatives = vec![];
+        let mut rest = range;
+
+        while !rest.is_empty() {
+            let end = rest.find([']', ')'])?;
+            let (set, tail) = rest.split_at(end + 1);
+            rest = tail.trim_start_matches(',').trim();
+
+            let lower_inclusive = set.starts_with('[');
+            let upper_inclusive = set.ends_with(']');
+            let inner = &set[1..set.len() - 1];
+
+            let mut bounds = vec![];
+            match inner.split_once(',') {
+                Some((lower, upper)) => {
+                    if !lower.trim().is_empty() {
+                        let op = if lower_inclusive { BoundOp::GreaterEq } else { BoundOp::Greater };
+                        bounds.push(Bound::new(op, lower));
+                    }
+                    if !upper.trim().is_empty() {
+                        let op = if upper_inclusive { BoundOp::LessEq } else { BoundOp::Less };
+                        bounds.push(Bound::new(op, upper));
+                    }
+                },
+                None => bounds.push(Bound::new(BoundOp::Eq, inner))
+            }
+
+            alternatives.push(bounds);
+        }
+
+        Some(VersionRange{alternatives})
+    }
+
+    pub fn contains(&self, version: &str) -> bool {
+        self.alternatives.iter().any(|bounds| bounds.iter().all(|b| b.matches(version)))
+    }
+
+    // Version if the range allows only one
+    pub fn as_exact(&self) -> Option<&str> {
+        match self.alternatives.as_slice() {
+            [bounds] => match bounds.as_slice() {
+                [Bound{op: BoundOp::Eq, version}] => Some(version),
+                _ => None
+            },
+            _ => None
+        }
+    }
+
+    // Whether some version `lower <= v < upper` is allowed
+    pub fn intersects(&self, lower: &str, upper: &str) -> bool {
+        self.alternatives.iter().any(|bounds| {
+            if let Some(exact) = bounds.iter().find(|b| b.op == BoundOp::Eq) {
+                return compare_versions(&exact.version, lower) != Ordering::Less
+                    && compare_versions(&exact.version, upper) == Ordering::Less
+                    && bounds.iter().all(|b| b.matches(&exact.version));
+            }
+
+            // Highest lower and lowest upper end with their inclusiveness
+            let mut from = (lower, true);
+            let mut to = (upper, false);
+            for bound in bounds {
+                match bound.op {
+                    BoundOp::Greater | BoundOp::GreaterEq => {
+                        let inclusive = bound.op == BoundOp::GreaterEq;
+                        match compare_versions(&bound.version, from.0) {
+                            Ordering::Greater => { from = (&bound.version, inclusive); },
+                            Ordering::Equal => { from.1 &= inclusive; },
+                            Ordering::Less => {}
+                        }
+                    },
+                    BoundOp::Less | BoundOp::LessEq => {
+                        let inclusive = bound.op == BoundOp::LessEq;
+                        match compare_versions(&bound.version, to.0) {
+                            Ordering::Less => { to = (&bound.version, inclusive); },
+                            Ordering::Equal => { to.1 &= inclusive; },
+                            Ordering::Greater => {}
+                        }
+                    },
+                    BoundOp::Eq => {}
+                }
+            }
+
+            match compare_versions(from.0, to.0) {
+                Ordering::Less => true,
+                Ordering::Equal => from.1 && to.1,
+                Ordering::Greater => false
+            }
+        })
+    }
+
+    // Lowest allowed version, None if there is no lower limit
+    pub fn minimum(&self) -> Option<(&str, bool)> {
+        let mut minimum: Option<(&str, bool)> = None;
+        for bounds in &self.alternatives {
+            let lower = bounds.iter().filter_map(|b| b.lower())
+                .max_by(|a, b| compare_versions(a.0, b.0).then(b.1.cmp(&a.1)));
+            let lower = lower?;
+
+            minimum = match minimum {
+                Some(current) if compare_versions(current.0, lower.0) != Ordering::Greater => Some(current),
+                _ => Some(lower)
+            };
+        }
+
+        minimum
+    }
+
+    // Highest allowed version, None if there is no upper limit
+    pub fn maximum(&self) -> Option<(&str, bool)> {
+        let mut maximum: Option<(&str, bool)> = None;
+        for bounds in &self.alternatives {
+            let upper = bounds.iter().filter_map(|b| b.upper())
+                .min_by(|a, b| compare_versions(a.0, b.0).then(a.1.cmp(&b.1)));
+            let upper = upper?;
+
+            maximum = match maximum {
+                Some(current) if compare_versions(current.0, upper.0) != Ordering::Less => Some(current),
+                _ => Some(upper)
+            };
+        }
+
+        maximum
+    }
+
+    // Whether some versions allowed by `self` are below everything `required` allows
+    pub fn allows_below(&self, required: &VersionRange) -> bool {
+        let required = match required.minimum() {
+            Some(min) => min,
+            None => { return false; }
+        };
+
+        match self.minimum() {
+            None => true,
+            Some((version, inclusive)) => match compare_versions(version, required.0) {
+                Ordering::Less => true,
+                Ordering::Equal => inclusive && !required.1,
+                Ordering::Greater => false
+            }
+        }
+    }
+
+    // Whether some versions allowed by `self` are above everything `required` allows
+    pub fn allows_above(&self, required: &VersionRange) -> bool {
+        let required = match required.maximum() {
+            Some(max) => max,
+            None => { return false; }
+        };
+
+        match self.maximum() {
+            None => true,
+            Some((version, inclusive)) => match compare_versions(version, required.0) {
+                Ordering::Greater => true,
+                Ordering::Equal => inclusive && !required.1,
+                Ordering::Less => false
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let alternatives: Vec<String> = self.alternatives.iter().map(|bounds| {
+            if bounds.is_empty() {
+                return "*".to_string();
+            }
+
+            bounds.iter().map(|b| {
+                let op = match b.op {
+                    BoundOp::Less => "<",
+                    BoundOp::LessEq => "<=",
+                    BoundOp::Greater => ">",
+                    BoundOp::GreaterEq => ">=",
+                    BoundOp::Eq => ""
+                };
+                format!("{}{}", op, b.version)
+            }).collect::<Vec<_>>().join(" ")
+        }).collect();
+
+        f.write_str(&alternatives.join(" || "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_versions_numerically() {
+        assert_eq!(compare_versions("47.10.0", "47.2"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0", "1.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("0.15.11", "0.16"), Ordering::Less);
+        assert_eq!(compare_versions("1.0+build.7", "1.0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn pre_releases_go_before_releases() {
+        assert_eq!(compare_versions("1.0-beta", "1.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.0", "1.0-rc.1"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0-alpha", "1.0-beta"), Ordering::Less);
+        assert_eq!(compare_versions("1.0.1-beta", "1.0"), Ordering::Greater);
+    }
+
+    #[test]
+    fn maven_ranges() {
+        let range = VersionRange::parse("[47.2,48)").unwrap();
+        assert!(range.contains("47.2"));
+        assert!(range.contains("47.2.0"));
+        assert!(range.contains("47.10.1"));
+        assert!(!range.contains("47.1.9"));
+        assert!(!range.contains("48"));
+        assert!(!range.contains("48.0.1"));
+
+        let range = VersionRange::parse("(,2.0]").unwrap();
+        assert!(range.contains("0.1"));
+        assert!(range.contains("2.0"));
+        assert!(!range.contains("2.0.1"));
+
+        let range = VersionRange::parse("[1.0,1.5),[2.0,)").unwrap();
+        assert!(range.contains("1.4"));
+        assert!(!range.contains("1.7"));
+        assert!(range.contains("3.0"));
+
+        assert_eq!(VersionRange::parse("[1.0]").unwrap().as_exact(), Some("1.0"));
+    }
+
+    #[test]
+    fn caret_and_tilde() {
+        let range = VersionRange::parse("^0.15").unwrap();
+        assert!(range.contains("0.15"));
+        assert!(range.contains("0.15.11"));
+        assert!(!range.contains("0.14.22"));
+        assert!(!range.contains("0.16.0"));
+
+        let range = VersionRange::parse("^1.2").unwrap();
+        assert!(range.contains("1.9"));
+        assert!(!range.contains("2.0"));
+
+        let range = VersionRange::parse("~1.2.3").unwrap();
+        assert!(range.contains("1.2.9"));
+        assert!(!range.contains("1.2.2"));
+        assert!(!range.contains("1.3"));
+    }
+
+    #[test]
+    fn comparators_and_wildcards() {
+        let range = VersionRange::parse(">=0.14 <0.16 || 1.x").unwrap();
+        assert!(range.contains("0.15.7"));
+        assert!(range.contains("1.4"));
+        assert!(!range.contains("0.16"));
+        assert!(!range.contains("2.0"));
+
+        let range = VersionRange::parse("1.20.x").unwrap();
+        assert!(range.contains("1.20"));
+        assert!(range.contains("1.20.4"));
+        assert!(!range.contains("1.21"));
+
+        assert!(VersionRange::parse("*").unwrap().contains("anything"));
+        assert!(VersionRange::parse("").unwrap().contains("1.0"));
+        assert!(VersionRange::parse("0.15.7").unwrap().contains("0.15.7"));
+        assert!(!VersionRange::parse("0.15.7").unwrap().contains("0.15.8"));
+    }
+
+    #[test]
+    fn displays_bounds() {
+        assert_eq!(VersionRange::parse("[47.2,48)").unwrap().to_string(), ">=47.2 <48");
+        assert_eq!(VersionRange::parse("^0.15").unwrap().to_string(), ">=0.15 <0.16");
+        assert_eq!(VersionRange::any().to_string(), "*");
+    }
+
+    #[test]
+    fn allows_versions_below_requirement() {
+        let required = VersionRange::parse(">=0.15").unwrap();
+        assert!(VersionRange::parse(">=0.14").unwrap().allows_below(&required));
+        assert!(VersionRange::parse("*").unwrap().allows_below(&required));
+        assert!(!VersionRange::parse(">=0.15").unwrap().allows_below(&required));
+        assert!(!VersionRange::parse("^0.15.1").unwrap().allows_below(&required));
+
+        let exclusive = VersionRange::parse("(0.15,)").unwrap();
+        assert!(VersionRange::parse("[0.15,)").unwrap().allows_below(&exclusive));
+        assert!(!VersionRange::parse("(0.15,)").unwrap().allows_below(&required));
+
+        assert!(!VersionRange::parse(">=0.1").unwrap().allows_below(&VersionRange::any()));
+    }
+
+    #[test]
+    fn allows_versions_above_requirement() {
+        let required = VersionRange::parse("[47,47.1)").unwrap();
+        assert!(VersionRange::parse("[47.2,48)").unwrap().allows_above(&required));
+        assert!(VersionRange::parse(">=47").unwrap().allows_above(&required));
+        assert!(VersionRange::parse("[47,47.1]").unwrap().allows_above(&required));
+        assert!(!VersionRange::parse("[47,47.1)").unwrap().allows_above(&required));
+        assert!(!VersionRange::parse("47.0.35").unwrap().allows_above(&required));
+
+        assert_eq!(VersionRange::parse("<1.0 || <=2.0").unwrap().maximum(), Some(("2.0", true)));
+        assert_eq!(VersionRange::parse("<1.0 || >=2.0").unwrap().maximum(), None);
+        assert!(!VersionRange::parse("*").unwrap().allows_above(&VersionRange::any()));
+    }
+}